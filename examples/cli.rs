@@ -12,8 +12,8 @@ use std::{
 
 use media_session::{MediaInfo, MediaSession};
 
-fn human_time(microsecs: i64) -> String {
-    let secs = microsecs / 1_000_000;
+fn human_time(time: Duration) -> String {
+    let secs = time.as_secs();
 
     format!("{}:{:02}", secs / 60, secs % 60)
 }
@@ -40,11 +40,11 @@ fn progress_bar(pos_percent: usize) -> String {
 }
 
 fn update(info: MediaInfo) {
-    let pos_percent: usize = (info.position as f64 / info.duration as f64 * 100.0) as usize;
+    let pos_percent = usize::from(info.progress_percent());
 
     let progress_bar = progress_bar(pos_percent);
-    let pos_str = human_time(info.position);
-    let dur_str = human_time(info.duration);
+    let pos_str = human_time(info.position_duration());
+    let dur_str = human_time(info.total_duration());
 
     let title = info.title;
     let artist = info.artist;
@@ -64,10 +64,170 @@ fn update(info: MediaInfo) {
     lock.flush().unwrap();
 }
 
+/// Waybar parses `text` and `tooltip` as Pango markup
+#[cfg(feature = "json")]
+fn escape_markup(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// One line for a waybar `custom` module (`"return-type": "json"`)
+#[cfg(feature = "json")]
+fn waybar_line(info: &MediaInfo) -> String {
+    let text = match (info.artist.is_empty(), info.title.is_empty()) {
+        (_, true) => String::new(),
+        (true, false) => info.title.clone(),
+        (false, false) => format!("{} - {}", info.artist, info.title),
+    };
+
+    let tooltip = if info.title.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "{}\n{}\n{}\n{} / {}",
+            info.title,
+            info.artist,
+            info.album_title,
+            human_time(info.position_duration()),
+            human_time(info.total_duration()),
+        )
+    };
+
+    json::object! {
+        text: escape_markup(&text),
+        tooltip: escape_markup(&tooltip),
+        class: info.state.as_str(),
+        percentage: info.progress_percent(),
+    }
+    .dump()
+}
+
+/// Print a line whenever it changes, until killed
+#[cfg(feature = "json")]
+fn run_waybar(player: &mut MediaSession) {
+    let mut last = String::new();
+
+    loop {
+        player.update();
+
+        let line = waybar_line(&player.get_info());
+        if line != last {
+            println!("{line}");
+            last = line;
+        }
+
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// `cli list`: one line per player, numbered for `cli player`
+#[cfg(feature = "cli")]
+fn list(player: &MediaSession) {
+    for (index, session) in player.list_sessions().iter().enumerate() {
+        let info = &session.info;
+        let track = match (info.artist.is_empty(), info.title.is_empty()) {
+            (_, true) => String::from("-"),
+            (true, false) => info.title.clone(),
+            (false, false) => format!("{} - {}", info.artist, info.title),
+        };
+
+        println!(
+            "{:>2}  {:<40}  {:<8}  {track}",
+            index + 1,
+            session.source_app_id,
+            info.state.as_str(),
+        );
+    }
+}
+
+/// `cli player <index> <command>`: control the player numbered `index` by `cli list`
+#[cfg(feature = "cli")]
+fn control(player: &mut MediaSession, index: &str, command: &str) -> Result<(), String> {
+    use media_session::MediaSessionControls;
+
+    let sessions = player.list_sessions();
+    let session = index
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| sessions.get(index.checked_sub(1)?))
+        .ok_or_else(|| format!("no player {index}, see `cli list`"))?;
+
+    player
+        .select_session(&session.source_app_id)
+        .map_err(|e| e.to_string())?;
+
+    match command {
+        "play" => player.play(),
+        "pause" => player.pause(),
+        "toggle" => player.toggle_pause(),
+        "stop" => player.stop(),
+        "next" => player.next(),
+        "prev" => player.prev(),
+        _ => return Err(format!("unknown command {command}")),
+    }
+    .map_err(|e| e.to_string())
+}
+
+enum Mode<'a> {
+    Display,
+    Waybar,
+    List,
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    Control { index: &'a str, command: &'a str },
+}
+
 fn main() {
-    // print!("\x1b[?25l");
+    const USAGE: &str = "usage: cli [--format json]\n       cli list\n       \
+                         cli player <index> play|pause|toggle|stop|next|prev";
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mode = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => Mode::Display,
+        ["--format", "json"] | ["--format=json"] => Mode::Waybar,
+        ["list"] => Mode::List,
+        ["player", index, command] => Mode::Control { index, command },
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let mut player = MediaSession::new().expect("no media session backend available");
+
+    match mode {
+        Mode::Display => {}
+        Mode::Waybar => {
+            #[cfg(feature = "json")]
+            run_waybar(&mut player);
+
+            #[cfg(not(feature = "json"))]
+            {
+                eprintln!("--format json needs the `json` feature");
+                std::process::exit(2);
+            }
+        }
+        #[cfg(feature = "cli")]
+        Mode::List => {
+            list(&player);
+            return;
+        }
+        #[cfg(feature = "cli")]
+        Mode::Control { index, command } => {
+            if let Err(e) = control(&mut player, index, command) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(not(feature = "cli"))]
+        Mode::List | Mode::Control { .. } => {
+            eprintln!("session commands need the `cli` feature");
+            std::process::exit(2);
+        }
+    }
 
-    let mut player = MediaSession::new();
+    // print!("\x1b[?25l");
 
     loop {
         player.update();
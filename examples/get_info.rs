@@ -1,8 +1,6 @@
-use media_session::MediaSession;
-
 fn main() {
-    let player = MediaSession::new();
-    let info = player.get_info();
-
-    println!("{info:#?}");
+    match media_session::now_playing().expect("no media session backend available") {
+        Some(info) => println!("{info:#?}"),
+        None => println!("Nothing is playing"),
+    }
 }
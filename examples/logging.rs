@@ -8,7 +8,7 @@ fn main() {
         .with_max_level(tracing::Level::DEBUG)
         .init();
 
-    let mut player = MediaSession::new();
+    let mut player = MediaSession::new().expect("no media session backend available");
 
     loop {
         player.update();
@@ -0,0 +1,93 @@
+use std::{
+    sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{builder::Config, MediaInfo, MediaSession};
+
+/// Keeps the thread started by
+/// [`MediaSession::spawn_auto_update`](crate::MediaSession::spawn_auto_update) running;
+/// dropping it stops the thread (and drops its session)
+pub struct AutoUpdateHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for AutoUpdateHandle {
+    fn drop(&mut self) {
+        // Disconnecting wakes the thread from its wait
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+/// Build a session from `config` on a new thread and `update` it every `interval`,
+/// sending the info after each update
+pub(crate) fn spawn(
+    config: Config,
+    interval: Duration,
+) -> crate::Result<(AutoUpdateHandle, Receiver<MediaInfo>)> {
+    let (stop, stopped) = channel::<()>();
+    let (sender, receiver) = channel();
+    let (built, build_result) = sync_channel(1);
+
+    // The session isn't `Send` on every platform, so it has to be created where it is used
+    let thread = std::thread::spawn(move || {
+        let mut session = match MediaSession::with_config(config) {
+            Ok(session) => {
+                _ = built.send(Ok(()));
+                session
+            }
+            Err(e) => {
+                _ = built.send(Err(e));
+                return;
+            }
+        };
+
+        loop {
+            session.update();
+
+            // Also stop once nobody listens
+            if sender.send(session.get_info()).is_err() {
+                return;
+            }
+
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    });
+
+    let handle = AutoUpdateHandle {
+        stop: Some(stop),
+        thread: Some(thread),
+    };
+
+    build_result
+        .recv()
+        .map_err(|_| crate::Error::new("update thread exited"))??;
+
+    Ok((handle, receiver))
+}
+
+#[cfg(all(test, feature = "stub"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stops_on_drop() {
+        let (handle, infos) = spawn(Config::default(), Duration::from_millis(10)).unwrap();
+
+        assert!(infos.recv().is_ok());
+
+        drop(handle);
+        // The thread is joined, so its sender is gone once the backlog is read
+        while infos.try_recv().is_ok() {}
+        assert!(infos.recv().is_err());
+    }
+}
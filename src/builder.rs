@@ -0,0 +1,219 @@
+use std::{sync::mpsc::Receiver, time::Duration};
+
+use crate::{utils::duration_micros, AutoUpdateHandle, MediaInfo, MediaSession, PositionInfo};
+
+pub(crate) const DEFAULT_THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(3);
+pub(crate) const DEFAULT_CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+/// Age of the player's last position report beyond which it is held, not extrapolated
+pub(crate) const DEFAULT_MAX_EXTRAPOLATION: Duration = Duration::from_secs(5);
+const DEFAULT_TRACK_HISTORY: usize = 20;
+
+/// Options shared by all backends
+#[derive(Clone, Debug)]
+pub(crate) struct Config {
+    pub clear_on_stop: bool,
+    pub transition_log_level: Option<tracing::Level>,
+    #[cfg_attr(
+        not(any(windows, all(unix, not(target_os = "macos"), feature = "remote-art"))),
+        allow(dead_code)
+    )]
+    pub thumbnail_timeout: Duration,
+    /// Read covers at all; off leaves the cover fields empty
+    #[cfg_attr(
+        any(target_os = "macos", feature = "stub", not(any(unix, windows))),
+        allow(dead_code)
+    )]
+    pub fetch_cover: bool,
+    #[cfg_attr(
+        any(target_os = "macos", feature = "stub", not(any(unix, windows))),
+        allow(dead_code)
+    )]
+    pub control_timeout: Duration,
+    pub priority: Vec<String>,
+    /// Hold the position rather than extrapolate from a report older than this
+    pub max_extrapolation: Duration,
+    /// Tracks kept for `recent_tracks`, `0` when off
+    pub track_history: usize,
+    /// Caller's runtime to use instead of one of our own
+    #[cfg(windows)]
+    #[cfg_attr(feature = "stub", allow(dead_code))]
+    pub runtime: Option<tokio::runtime::Handle>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clear_on_stop: false,
+            transition_log_level: None,
+            thumbnail_timeout: DEFAULT_THUMBNAIL_TIMEOUT,
+            fetch_cover: true,
+            control_timeout: DEFAULT_CONTROL_TIMEOUT,
+            priority: Vec::new(),
+            max_extrapolation: DEFAULT_MAX_EXTRAPOLATION,
+            track_history: 0,
+            #[cfg(windows)]
+            runtime: None,
+        }
+    }
+}
+
+impl Config {
+    /// Position of the first `priority` entry matching `app_id`; unmatched ids rank last
+    #[cfg_attr(any(feature = "stub", not(any(unix, windows))), allow(dead_code))]
+    pub fn priority_rank(&self, app_id: &str) -> usize {
+        let app_id = app_id.to_lowercase();
+
+        self.priority
+            .iter()
+            .position(|p| app_id.contains(&p.to_lowercase()))
+            .unwrap_or(self.priority.len())
+    }
+
+    /// An empty [`PositionInfo`] honouring `max_extrapolation`
+    #[cfg_attr(any(feature = "stub", not(any(unix, windows))), allow(dead_code))]
+    pub fn position_info(&self) -> PositionInfo {
+        PositionInfo {
+            max_extrapolation: duration_micros(self.max_extrapolation),
+            ..Default::default()
+        }
+    }
+
+    /// `get_info` reports the default [`MediaInfo`] instead of `info`
+    pub fn hides(&self, info: &MediaInfo) -> bool {
+        self.clear_on_stop && info.is_stopped()
+    }
+
+    /// Apply the configured presentation rules to a freshly built [`MediaInfo`]
+    pub fn finalize(&self, info: MediaInfo) -> MediaInfo {
+        if self.hides(&info) {
+            return MediaInfo::default();
+        }
+
+        info
+    }
+}
+
+#[derive(Default)]
+pub struct MediaSessionBuilder {
+    config: Config,
+}
+
+impl MediaSessionBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When `true`, a stopped (or closed) player makes `get_info` return the default [`MediaInfo`].
+    /// Otherwise (default) the last track is kept with position reset to 0.
+    #[must_use]
+    pub fn clear_on_stop(mut self, clear: bool) -> Self {
+        self.config.clear_on_stop = clear;
+        self
+    }
+
+    /// Log every [`MediaEvent`](crate::MediaEvent) (track change, state change, seek)
+    /// detected on `update` at `level`. Off by default.
+    #[must_use]
+    pub fn log_transitions(mut self, level: tracing::Level) -> Self {
+        self.config.transition_log_level = Some(level);
+        self
+    }
+
+    /// Preferred players, most wanted first, matched as case-insensitive substrings of
+    /// the source app id (Windows) or bus name (Linux), e.g. `["spotify", "firefox"]`.
+    ///
+    /// When several players are available, the session is picked by:
+    /// 1. position in this list (unmatched players last)
+    /// 2. playing over paused/stopped
+    /// 3. recency: the system's current session on Windows, the already selected
+    ///    player on Linux, then the order players are reported in
+    #[must_use]
+    pub fn priority<I, S>(mut self, players: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.priority = players.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Give up reading the cover after `timeout` and report no cover instead (default 3s).
+    ///
+    /// Affects Windows, where the app provides the cover as a stream, and cover
+    /// downloads on Linux with the `remote-art` feature.
+    #[must_use]
+    pub fn thumbnail_timeout(mut self, timeout: Duration) -> Self {
+        self.config.thumbnail_timeout = timeout;
+        self
+    }
+
+    /// Whether to read covers at all (default `true`). See
+    /// [`MediaSession::set_fetch_cover`].
+    #[must_use]
+    pub fn fetch_cover(mut self, fetch: bool) -> Self {
+        self.config.fetch_cover = fetch;
+        self
+    }
+
+    /// Fail controls that take longer than `timeout` with [`ErrorKind::Timeout`]
+    /// (default 5s). See [`MediaSession::set_control_timeout`].
+    ///
+    /// [`ErrorKind::Timeout`]: crate::ErrorKind::Timeout
+    #[must_use]
+    pub fn control_timeout(mut self, timeout: Duration) -> Self {
+        self.config.control_timeout = timeout;
+        self
+    }
+
+    /// Stop extrapolating the position once the player's last report is more than `max`
+    /// old (default 5s). See [`MediaSession::set_max_extrapolation`].
+    #[must_use]
+    pub fn max_extrapolation(mut self, max: Duration) -> Self {
+        self.config.max_extrapolation = max;
+        self
+    }
+
+    /// Remember the last 20 tracks for [`MediaSession::recent_tracks`] (off by default)
+    #[must_use]
+    pub fn track_history(self, enabled: bool) -> Self {
+        self.track_history_size(if enabled { DEFAULT_TRACK_HISTORY } else { 0 })
+    }
+
+    /// Remember the last `size` tracks for [`MediaSession::recent_tracks`] (`0`: off)
+    #[must_use]
+    pub fn track_history_size(mut self, size: usize) -> Self {
+        self.config.track_history = size;
+        self
+    }
+
+    /// Drive the Windows API calls on the caller's tokio runtime rather than on one
+    /// the session creates, which panics when the session is used from async code
+    /// ("Cannot start a runtime from within a runtime").
+    ///
+    /// Within the runtime, session methods block through
+    /// [`block_in_place`](tokio::task::block_in_place), so it must be multi-threaded;
+    /// it also needs its time driver for control timeouts. Windows only.
+    #[cfg(windows)]
+    #[must_use]
+    pub fn use_existing_runtime(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.config.runtime = Some(handle);
+        self
+    }
+
+    pub fn build(self) -> crate::Result<MediaSession> {
+        MediaSession::with_config(self.config)
+    }
+
+    /// Build the session on a background thread that calls `update` every `interval`
+    /// and sends the fresh [`MediaInfo`] after each one.
+    ///
+    /// The thread stops when the [`AutoUpdateHandle`] or the receiver is dropped.
+    /// Fails like [`build`](Self::build).
+    pub fn spawn_auto_update(
+        self,
+        interval: Duration,
+    ) -> crate::Result<(AutoUpdateHandle, Receiver<MediaInfo>)> {
+        crate::auto_update::spawn(self.config, interval)
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::MediaInfo;
+
+/// Runs a callback set with
+/// [`MediaSession::set_callback_send`](crate::MediaSession::set_callback_send) on
+/// whichever thread calls [`poll_callbacks`](Self::poll_callbacks), e.g. a UI thread.
+///
+/// Every `update` queues its [`MediaInfo`] until polled, so poll regularly or drop the
+/// queue.
+pub struct CallbackQueue {
+    receiver: Receiver<MediaInfo>,
+    callback: Box<dyn Fn(MediaInfo) + Send>,
+}
+
+impl CallbackQueue {
+    pub(crate) fn new(callback: Box<dyn Fn(MediaInfo) + Send>) -> (Sender<MediaInfo>, Self) {
+        let (sender, receiver) = channel();
+        (sender, Self { receiver, callback })
+    }
+
+    /// Call the callback for each update queued since the last poll, oldest first.
+    /// Returns how many there were.
+    pub fn poll_callbacks(&self) -> usize {
+        let mut count = 0;
+        for info in self.receiver.try_iter() {
+            (self.callback)(info);
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_runs_on_polling_thread() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let (sender, queue) = CallbackQueue::new(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        sender.send(MediaInfo::default()).unwrap();
+        sender.send(MediaInfo::default()).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let polled = std::thread::spawn(move || queue.poll_callbacks())
+            .join()
+            .unwrap();
+        assert_eq!(polled, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
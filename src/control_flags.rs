@@ -0,0 +1,52 @@
+use crate::PlaybackCapabilities;
+
+bitflags::bitflags! {
+    /// [`PlaybackCapabilities`] as a set, for checking several controls at once.
+    ///
+    /// ```
+    /// use media_session::{ControlFlags, PlaybackCapabilities};
+    ///
+    /// let capabilities = PlaybackCapabilities {
+    ///     can_play: true,
+    ///     can_pause: true,
+    ///     ..Default::default()
+    /// };
+    /// let controls = ControlFlags::from(capabilities);
+    ///
+    /// assert!(controls.contains(ControlFlags::PLAY | ControlFlags::PAUSE));
+    /// assert!(!controls.contains(ControlFlags::SEEK));
+    /// ```
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct ControlFlags: u16 {
+        const PLAY = 1 << 0;
+        const PAUSE = 1 << 1;
+        const STOP = 1 << 2;
+        const NEXT = 1 << 3;
+        const PREV = 1 << 4;
+        const SEEK = 1 << 5;
+        const SHUFFLE = 1 << 6;
+        const REPEAT = 1 << 7;
+        const RATE = 1 << 8;
+        const VOLUME = 1 << 9;
+    }
+}
+
+impl From<PlaybackCapabilities> for ControlFlags {
+    fn from(capabilities: PlaybackCapabilities) -> Self {
+        [
+            (capabilities.can_play, Self::PLAY),
+            (capabilities.can_pause, Self::PAUSE),
+            (capabilities.can_stop, Self::STOP),
+            (capabilities.can_next, Self::NEXT),
+            (capabilities.can_prev, Self::PREV),
+            (capabilities.can_seek, Self::SEEK),
+            (capabilities.can_shuffle, Self::SHUFFLE),
+            (capabilities.can_repeat, Self::REPEAT),
+            (capabilities.can_set_rate, Self::RATE),
+            (capabilities.can_set_volume, Self::VOLUME),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| *supported)
+        .fold(Self::empty(), |flags, (_, flag)| flags | flag)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{io::Cursor, sync::Mutex};
+
+use image::{imageops::FilterType, ImageFormat};
+
+/// Decode `raw`, scale it down to fit in a `max_dim` square (keeping aspect ratio)
+/// and re-encode it as PNG
+pub(crate) fn resize(raw: &[u8], max_dim: u32) -> Option<Vec<u8>> {
+    if raw.is_empty() || max_dim == 0 {
+        return None;
+    }
+
+    let image = image::load_from_memory(raw)
+        .inspect_err(|e| tracing::warn!("Failed to decode cover: {e}"))
+        .ok()?;
+
+    let image = if image.width() > max_dim || image.height() > max_dim {
+        image.resize(max_dim, max_dim, FilterType::Lanczos3)
+    } else {
+        image
+    };
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .inspect_err(|e| tracing::warn!("Failed to encode cover: {e}"))
+        .ok()?;
+
+    Some(png)
+}
+
+struct Resized {
+    source_hash: u64,
+    max_dim: u32,
+    data: Option<Vec<u8>>,
+}
+
+/// Keeps the last resized cover, so repeated requests for the same size are free
+#[derive(Default)]
+pub(crate) struct ResizeCache {
+    last: Mutex<Option<Resized>>,
+}
+
+impl ResizeCache {
+    /// `raw` resized, `source_hash` being its `cover_hash`
+    pub fn get(&self, raw: &[u8], source_hash: u64, max_dim: u32) -> Option<Vec<u8>> {
+        let mut last = self.last.lock().unwrap();
+
+        if let Some(resized) = last.as_ref() {
+            if resized.source_hash == source_hash && resized.max_dim == max_dim {
+                return resized.data.clone();
+            }
+        }
+
+        let data = resize(raw, max_dim);
+        *last = Some(Resized {
+            source_hash,
+            max_dim,
+            data: data.clone(),
+        });
+
+        data
+    }
+}
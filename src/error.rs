@@ -1,38 +1,146 @@
+/// What went wrong, for deciding whether to retry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// There is no player (or not the requested one) to act on
+    NoActiveSession,
+    /// The player or platform can't do this
+    Unsupported,
+    /// The platform API failed; holds its error code (HRESULT, DBus error name) when
+    /// there is one
+    Backend(String),
+    Io,
+    /// The platform or player didn't answer in time, or the connection to it dropped;
+    /// retrying may help
+    Timeout,
+}
+
 #[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     message: String,
+    code: Option<i32>,
 }
 
 impl Error {
+    /// A [`ErrorKind::Backend`] error without a code
     pub fn new<T: Into<String>>(message: T) -> Self {
+        Self::with_kind(ErrorKind::Backend(String::new()), message)
+    }
+
+    pub fn with_kind<T: Into<String>>(kind: ErrorKind, message: T) -> Self {
         Self {
+            kind,
             message: message.into(),
+            code: None,
         }
     }
+
+    /// Attach the platform's numeric error code
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn with_code(mut self, code: i32) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub(crate) fn unsupported<T: Into<String>>(message: T) -> Self {
+        Self::with_kind(ErrorKind::Unsupported, message)
+    }
+
+    pub(crate) fn no_active_session<T: Into<String>>(message: T) -> Self {
+        Self::with_kind(ErrorKind::NoActiveSession, message)
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The platform's numeric error code: the HRESULT on Windows, `None` elsewhere
+    #[must_use]
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        core::write!(f, "{}", self.message)
+        match self.code {
+            // HRESULTs are conventionally written as unsigned hex, e.g. 0x80070005
+            Some(code) => core::write!(f, "{} ({code:#010x})", self.message),
+            None => core::write!(f, "{}", self.message),
+        }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        let kind = match e.kind() {
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            _ => ErrorKind::Io,
+        };
+
+        Self::with_kind(kind, e.to_string())
+    }
+}
+
 #[cfg(windows)]
 impl From<windows::core::Error> for Error {
     fn from(e: windows::core::Error) -> Self {
-        Self {
-            message: e.message(),
-        }
+        // Values from winerror.h
+        const E_NOTIMPL: u32 = 0x8000_4001;
+        const RPC_E_DISCONNECTED: u32 = 0x8001_0108;
+        const RPC_S_SERVER_UNAVAILABLE: u32 = 0x8007_06BA;
+        const ERROR_TIMEOUT: u32 = 0x8007_05B4;
+
+        #[allow(clippy::cast_sign_loss, reason = "HRESULTs are read as unsigned")]
+        let code = e.code().0 as u32;
+
+        let kind = match code {
+            E_NOTIMPL => ErrorKind::Unsupported,
+            // The player's process went away mid-call
+            RPC_E_DISCONNECTED | RPC_S_SERVER_UNAVAILABLE | ERROR_TIMEOUT => ErrorKind::Timeout,
+            _ => ErrorKind::Backend(format!("{code:#010x}")),
+        };
+
+        Self::with_kind(kind, e.message()).with_code(e.code().0)
     }
 }
 
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos")))]
 impl From<dbus::Error> for Error {
     fn from(value: dbus::Error) -> Self {
-        Self {
-            message: value.message().unwrap_or("Unknown error").to_string(),
-        }
+        let name = value.name().unwrap_or_default();
+
+        let kind = match name {
+            "org.freedesktop.DBus.Error.NoReply" | "org.freedesktop.DBus.Error.Timeout" => {
+                ErrorKind::Timeout
+            }
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+            | "org.freedesktop.DBus.Error.NameHasNoOwner" => ErrorKind::NoActiveSession,
+            "org.freedesktop.DBus.Error.UnknownMethod"
+            | "org.freedesktop.DBus.Error.UnknownProperty"
+            | "org.freedesktop.DBus.Error.NotSupported" => ErrorKind::Unsupported,
+            _ => ErrorKind::Backend(name.to_owned()),
+        };
+
+        Self::with_kind(kind, value.message().unwrap_or("Unknown error"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_appends_code() {
+        #[allow(clippy::cast_possible_wrap, reason = "HRESULTs are read as unsigned")]
+        let access_denied = 0x8007_0005_u32 as i32;
+        let error = Error::new("Access is denied.").with_code(access_denied);
+
+        assert_eq!(error.code(), Some(access_denied));
+        assert_eq!(error.to_string(), "Access is denied. (0x80070005)");
+        assert_eq!(Error::new("Failed").to_string(), "Failed");
     }
 }
@@ -0,0 +1,166 @@
+//! C interface, for consumers outside Rust (e.g. a C++ native addon).
+//!
+//! Build the crate as a `cdylib` or `staticlib` with the `ffi` feature, e.g.
+//! `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! Ownership:
+//! - [`ms_new`] returns a session owned by the caller, released with [`ms_free`]
+//! - [`ms_get_info_json`] returns a string owned by the caller, released with
+//!   [`ms_string_free`] (never with `free`)
+//!
+//! Sessions are not thread-safe: use each from one thread at a time. Functions
+//! returning `c_int` return `0` on success and `-1` on failure or a null session.
+
+use std::{
+    ffi::{c_char, c_int, CString},
+    ptr,
+};
+
+use crate::{traits::MediaSessionControls, MediaSession};
+
+/// A new session, or null if the platform API is unavailable
+#[no_mangle]
+pub extern "C" fn ms_new() -> *mut MediaSession {
+    MediaSession::new().map_or(ptr::null_mut(), |session| Box::into_raw(Box::new(session)))
+}
+
+/// Release a session from [`ms_new`]; null is ignored.
+///
+/// # Safety
+/// `session` must come from [`ms_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ms_free(session: *mut MediaSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// Process pending player events, see [`MediaSession::update`]
+///
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_update(session: *mut MediaSession) -> c_int {
+    let Some(session) = session.as_mut() else {
+        return -1;
+    };
+
+    session.update();
+    0
+}
+
+/// The current [`MediaInfo`](crate::MediaInfo) as a JSON object (see
+/// [`MediaInfo::to_json_string`](crate::MediaInfo::to_json_string)), or null for a
+/// null session. Release it with [`ms_string_free`].
+///
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_get_info_json(session: *mut MediaSession) -> *mut c_char {
+    let Some(session) = session.as_ref() else {
+        return ptr::null_mut();
+    };
+
+    // JSON escapes control characters, so there is no interior NUL
+    CString::new(session.get_info().to_json_string()).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Release a string from [`ms_get_info_json`]; null is ignored.
+///
+/// # Safety
+/// `string` must come from this library and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ms_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Run `control` on `session`, mapping the outcome to a status code
+unsafe fn control<F>(session: *mut MediaSession, control: F) -> c_int
+where
+    F: FnOnce(&MediaSession) -> crate::Result<()>,
+{
+    let Some(session) = session.as_ref() else {
+        return -1;
+    };
+
+    match control(session) {
+        Ok(()) => 0,
+        Err(e) => {
+            tracing::warn!("FFI control failed: {e}");
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_play(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::play)
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_pause(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::pause)
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_toggle_pause(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::toggle_pause)
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_stop(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::stop)
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_next(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::next)
+}
+
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_prev(session: *mut MediaSession) -> c_int {
+    control(session, MediaSession::prev)
+}
+
+/// Seek to `position_micros` from the start of the track
+///
+/// # Safety
+/// `session` must be null or come from [`ms_new`].
+#[no_mangle]
+pub unsafe extern "C" fn ms_set_position(
+    session: *mut MediaSession,
+    position_micros: i64,
+) -> c_int {
+    control(session, |s| s.set_position(position_micros))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_session() {
+        unsafe {
+            assert_eq!(ms_update(ptr::null_mut()), -1);
+            assert_eq!(ms_play(ptr::null_mut()), -1);
+            assert!(ms_get_info_json(ptr::null_mut()).is_null());
+
+            ms_string_free(ptr::null_mut());
+            ms_free(ptr::null_mut());
+        }
+    }
+}
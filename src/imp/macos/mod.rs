@@ -0,0 +1,401 @@
+//! macOS backend.
+//!
+//! The system-wide "Now Playing" state lives in the private `MediaRemote` framework,
+//! which needs Objective-C blocks to call and rejects unentitled processes on recent
+//! macOS versions. Instead, with the `applescript` feature, Music and Spotify are
+//! queried and controlled through `osascript` (the first use asks the user for
+//! Automation access). Without it no session is ever reported and controls are
+//! unsupported. Covers are not read either way.
+
+mod script;
+
+use crate::{
+    builder::Config, media_event::EventDispatcher, traits, utils::micros_since_epoch, Error,
+    MediaInfo, PlaybackCapabilities, PositionInfo, RepeatMode, SessionHandle,
+};
+
+use script::{Player, PLAYERS};
+
+/// How often streams and `wait_for_session` re-run the scripts, as nothing notifies us
+/// of changes
+const STREAM_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn unsupported() -> crate::Result<()> {
+    Err(Error::unsupported(
+        "media controls are not supported on macOS without the applescript feature",
+    ))
+}
+
+pub struct MediaSession {
+    config: Config,
+    events: EventDispatcher,
+    player: Option<&'static Player>,
+    /// Bundle id picked with `select_session`
+    pinned: Option<&'static str>,
+    media_info: Option<MediaInfo>,
+    pos_info: PositionInfo,
+    /// Set by `close`: no player is read again and `update` does nothing
+    closed: bool,
+    #[cfg(feature = "image")]
+    resize_cache: crate::cover::ResizeCache,
+}
+
+impl MediaSession {
+    pub fn new() -> crate::Result<Self> {
+        Self::with_config(Config::default())
+    }
+
+    pub(crate) fn with_config(config: Config) -> crate::Result<Self> {
+        let mut session = Self {
+            events: EventDispatcher::with_history(config.track_history),
+            pos_info: config.position_info(),
+            config,
+            player: None,
+            pinned: None,
+            media_info: None,
+            closed: false,
+            #[cfg(feature = "image")]
+            resize_cache: crate::cover::ResizeCache::default(),
+        };
+
+        session.update();
+        Ok(session)
+    }
+
+    /// Every running player with what it is playing
+    fn running_players() -> Vec<(&'static Player, MediaInfo, i64)> {
+        PLAYERS
+            .iter()
+            .filter_map(|player| {
+                player
+                    .read()
+                    .inspect_err(|e| tracing::debug!(player = player.app, "Failed to read: {e}"))
+                    .ok()
+                    .flatten()
+                    .map(|(info, position)| (player, info, position))
+            })
+            .collect()
+    }
+
+    /// Pinned player first, then by priority, playing ones, the current one
+    fn select_player(
+        &self,
+        running: Vec<(&'static Player, MediaInfo, i64)>,
+    ) -> Option<(&'static Player, MediaInfo, i64)> {
+        let current = self.player.map(|p| p.id);
+
+        running.into_iter().min_by_key(|(player, info, _)| {
+            (
+                self.pinned != Some(player.id),
+                self.config.priority_rank(player.id),
+                !info.is_playing(),
+                current != Some(player.id),
+            )
+        })
+    }
+
+    pub fn update(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        let selected = self.select_player(Self::running_players());
+        let previous = self.player.map(|p| p.id);
+
+        match selected {
+            Some((player, info, position)) => {
+                if previous != Some(player.id) {
+                    self.events.session_changed(Some(player.id.to_owned()));
+                }
+
+                self.player = Some(player);
+                self.media_info = Some(info);
+                self.pos_info = PositionInfo {
+                    pos_raw: position,
+                    pos_last_update: micros_since_epoch(),
+                    playback_rate: 1.0,
+                    ..self.config.position_info()
+                };
+            }
+            None => {
+                if previous.is_some() {
+                    self.events.session_changed(None);
+                }
+
+                self.player = None;
+                self.media_info = None;
+            }
+        }
+
+        let info = self.get_info();
+        self.events.dispatch(info, self.config.transition_log_level);
+    }
+
+    /// Re-read the position from the player
+    pub fn resync_position(&mut self) {
+        let Some(player) = self.player else {
+            return;
+        };
+
+        match player.position() {
+            Ok(position) => {
+                self.pos_info.pos_raw = position;
+                self.pos_info.pos_last_update = micros_since_epoch();
+            }
+            Err(e) => tracing::warn!("Failed to resync position: {e}"),
+        }
+    }
+
+    /// Whether there is a player to control; controls are no-ops (`Ok(())`) otherwise
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        self.player.is_some()
+    }
+
+    #[must_use]
+    pub fn get_capabilities(&self) -> PlaybackCapabilities {
+        self.player
+            .map_or_else(PlaybackCapabilities::default, |player| {
+                PlaybackCapabilities {
+                    can_play: true,
+                    can_pause: true,
+                    can_stop: player.can_stop(),
+                    can_next: true,
+                    can_prev: true,
+                    can_seek: true,
+                    can_shuffle: true,
+                    can_repeat: true,
+                    can_set_rate: false,
+                    can_set_volume: true,
+                }
+            })
+    }
+
+    /// Woken every [`STREAM_POLL_INTERVAL`] until the stream holding it is dropped
+    #[allow(clippy::unused_self)]
+    pub(crate) fn notifier(&self) -> crate::notify::Notify {
+        crate::notify::Notify::ticker(STREAM_POLL_INTERVAL)
+    }
+
+    /// Always: only running the scripts tells
+    #[allow(clippy::unused_self)]
+    pub(crate) fn session_appeared(&self) -> bool {
+        true
+    }
+
+    /// Every running supported player, identified by bundle id (e.g. `com.apple.Music`)
+    #[must_use]
+    #[allow(clippy::unused_self)]
+    pub fn list_sessions(&self) -> Vec<SessionHandle> {
+        Self::running_players()
+            .into_iter()
+            .map(|(player, info, position)| SessionHandle {
+                source_app_id: player.id.to_owned(),
+                info: MediaInfo { position, ..info },
+            })
+            .collect()
+    }
+
+    /// Pin the session to the player with bundle id (or app name) `app_id`.
+    ///
+    /// While the player is gone the usual selection applies. Fails if no such player is
+    /// running now.
+    pub fn select_session(&mut self, app_id: &str) -> crate::Result<()> {
+        if self.closed {
+            return Err(Error::no_active_session("the session is closed"));
+        }
+
+        let player = PLAYERS
+            .iter()
+            .find(|p| p.id == app_id || p.app.eq_ignore_ascii_case(app_id))
+            .filter(|p| p.read().is_ok_and(|read| read.is_some()))
+            .ok_or_else(|| Error::no_active_session(format!("no session for {app_id}")))?;
+
+        self.pinned = Some(player.id);
+        self.update();
+        Ok(())
+    }
+
+    /// Go back to picking the player by priority
+    pub fn unpin_session(&mut self) {
+        self.pinned = None;
+        self.update();
+    }
+
+    pub(crate) fn close_impl(&mut self) {
+        if self.player.take().is_some() {
+            self.events.session_changed(None);
+        }
+
+        self.media_info = None;
+        self.pos_info = self.config.position_info();
+        self.closed = true;
+    }
+
+    pub(crate) fn set_max_extrapolation_impl(&mut self, max: std::time::Duration) {
+        self.config.max_extrapolation = max;
+        self.pos_info.max_extrapolation = self.config.position_info().max_extrapolation;
+    }
+
+    /// No-op: covers are not read on macOS
+    #[allow(clippy::unused_self)]
+    pub(crate) fn set_fetch_cover_impl(&mut self, _fetch: bool) {}
+
+    /// Playback state of every running player
+    #[allow(clippy::unused_self)]
+    pub(crate) fn session_states(&self) -> Vec<crate::PlaybackState> {
+        Self::running_players()
+            .into_iter()
+            .map(|(_, info, _)| info.state)
+            .collect()
+    }
+
+    /// Not enforced here
+    #[allow(clippy::unused_self)]
+    pub(crate) fn set_control_timeout_impl(&mut self, _timeout: std::time::Duration) {}
+
+    pub(crate) fn position_info(&self) -> PositionInfo {
+        self.pos_info.clone()
+    }
+
+    pub(crate) fn current_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref()
+    }
+
+    pub(crate) fn events(&self) -> &EventDispatcher {
+        &self.events
+    }
+
+    pub(crate) fn events_mut(&mut self) -> &mut EventDispatcher {
+        &mut self.events
+    }
+
+    #[must_use]
+    pub fn get_info(&self) -> MediaInfo {
+        let info = self
+            .media_info
+            .as_ref()
+            .map_or_else(MediaInfo::default, |info| {
+                info.with_position(&self.pos_info)
+            });
+
+        self.config.finalize(info)
+    }
+
+    /// Stream the current cover into `writer`. Covers are not read on macOS.
+    #[allow(clippy::unused_self)]
+    pub fn write_cover_to(&self, _writer: impl std::io::Write) -> crate::Result<()> {
+        Err(Error::new("no cover"))
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn resize_cache(&self) -> &crate::cover::ResizeCache {
+        &self.resize_cache
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Run `command` on the current player; `Ok(())` without one
+    fn control(&self, command: impl FnOnce(&Player) -> crate::Result<()>) -> crate::Result<()> {
+        if PLAYERS.is_empty() {
+            return unsupported();
+        }
+
+        self.player.map_or(Ok(()), command)
+    }
+}
+
+impl traits::MediaSessionControls for MediaSession {
+    fn next(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("next track"))
+    }
+    fn pause(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("pause"))
+    }
+    fn play(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("play"))
+    }
+    fn prev(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("previous track"))
+    }
+    fn stop(&self) -> crate::Result<()> {
+        self.control(|p| {
+            if p.can_stop() {
+                p.tell("stop")
+            } else {
+                Err(Error::unsupported("player does not support stopping"))
+            }
+        })
+    }
+    fn toggle_pause(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("playpause"))
+    }
+    fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        self.control(|p| {
+            p.tell(&format!(
+                "set player position to {}",
+                script::seconds(position_micros.max(0))
+            ))
+        })
+    }
+    fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        // The player clamps it to the track
+        self.control(|p| {
+            p.tell(&format!(
+                "set player position to (player position + {})",
+                script::seconds(offset_micros)
+            ))
+        })
+    }
+    fn set_volume(&self, level: f64) -> crate::Result<()> {
+        #[allow(clippy::cast_possible_truncation, reason = "0..=100")]
+        let volume = (level.clamp(0.0, 1.0) * 100.0).round() as u8;
+
+        self.control(|p| p.tell(&format!("set sound volume to {volume}")))
+    }
+    fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        self.control(|p| p.set_shuffle(on))
+    }
+    fn set_repeat(&self, mode: RepeatMode) -> crate::Result<()> {
+        self.control(|p| p.set_repeat(mode))
+    }
+    fn set_rate(&self, _rate: f64) -> crate::Result<()> {
+        // Neither scripting dictionary has a rate
+        self.control(|_| {
+            Err(Error::unsupported(
+                "player does not support changing the rate",
+            ))
+        })
+    }
+    fn supported_controls(&self) -> crate::ControlFlags {
+        self.get_capabilities().into()
+    }
+}
+
+impl traits::MediaSessionApp for MediaSession {
+    fn raise(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("activate"))
+    }
+    fn quit(&self) -> crate::Result<()> {
+        self.control(|p| p.tell("quit"))
+    }
+    // Every scriptable application handles both
+    fn can_raise(&self) -> bool {
+        self.player.is_some()
+    }
+    fn can_quit(&self) -> bool {
+        self.player.is_some()
+    }
+}
+
+impl traits::MediaSessionBackend for MediaSession {
+    fn backend_name(&self) -> &'static str {
+        "AppleScript"
+    }
+    fn platform(&self) -> crate::Platform {
+        crate::Platform::MacOs
+    }
+}
@@ -0,0 +1,225 @@
+//! Players driven through `osascript`.
+//!
+//! Each read is one `osascript` run printing the state and track fields, one per line.
+//! A player that isn't installed fails to compile its script, which reads as absent.
+
+use std::process::Command;
+
+use crate::{Error, MediaInfo, MediaType, PlaybackState, RepeatMode};
+
+/// Number of lines printed by [`Player::read`] for a loaded track
+const FIELD_COUNT: usize = 11;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Music,
+    Spotify,
+}
+
+pub struct Player {
+    /// Bundle id, reported as `source_app_id`
+    pub id: &'static str,
+    /// Name for `tell application`
+    pub app: &'static str,
+    dialect: Dialect,
+}
+
+const MUSIC: Player = Player {
+    id: "com.apple.Music",
+    app: "Music",
+    dialect: Dialect::Music,
+};
+
+const SPOTIFY: Player = Player {
+    id: "com.spotify.client",
+    app: "Spotify",
+    dialect: Dialect::Spotify,
+};
+
+/// Supported players, none without the `applescript` feature
+pub const PLAYERS: &[Player] = if cfg!(feature = "applescript") {
+    &[MUSIC, SPOTIFY]
+} else {
+    &[]
+};
+
+impl Player {
+    /// What the player is playing, with its position; `None` when it isn't running
+    pub fn read(&self) -> crate::Result<Option<(MediaInfo, i64)>> {
+        let (track_id, shuffle, repeat) = match self.dialect {
+            Dialect::Music => ("persistent ID", "shuffle enabled", "song repeat"),
+            Dialect::Spotify => ("id", "shuffling", "repeating"),
+        };
+
+        let output = osascript(&format!(
+            r#"if application "{app}" is running then
+    tell application "{app}"
+        set s to player state as string
+        if s is "stopped" then return s
+        set t to current track
+        return s & linefeed & (name of t) & linefeed & (artist of t) & linefeed & (album of t) & linefeed & (album artist of t) & linefeed & ({track_id} of t) & linefeed & (duration of t) & linefeed & player position & linefeed & sound volume & linefeed & {shuffle} & linefeed & ({repeat} as string)
+    end tell
+end if
+return """#,
+            app = self.app,
+        ))?;
+
+        if output.is_empty() {
+            return Ok(None);
+        }
+
+        self.parse(&output).map(Some)
+    }
+
+    fn parse(&self, output: &str) -> crate::Result<(MediaInfo, i64)> {
+        let lines: Vec<&str> = output.lines().collect();
+
+        let state = match lines.first().copied().unwrap_or_default() {
+            "playing" | "fast forwarding" | "rewinding" => PlaybackState::Playing,
+            "paused" => PlaybackState::Paused,
+            _ => PlaybackState::Stopped,
+        };
+
+        if matches!(state, PlaybackState::Stopped) {
+            let info = MediaInfo {
+                state,
+                source_app_id: self.id.to_owned(),
+                ..Default::default()
+            };
+            return Ok((info, 0));
+        }
+
+        let fields: [&str; FIELD_COUNT] = lines
+            .try_into()
+            .map_err(|_| Error::new(format!("unexpected output from {}", self.app)))?;
+        let [_, title, artist, album, album_artist, id, duration, pos, volume, shuffle, repeat] =
+            fields;
+
+        // Spotify reports the duration in milliseconds, Music in seconds
+        let duration_scale = match self.dialect {
+            Dialect::Music => 1_000_000.0,
+            Dialect::Spotify => 1_000.0,
+        };
+
+        let mut info = MediaInfo {
+            title: title.to_owned(),
+            artist: artist.to_owned(),
+            artists: [artist]
+                .into_iter()
+                .filter(|a| !a.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            album_title: album.to_owned(),
+            album_artist: album_artist.to_owned(),
+            track_id: id.to_owned(),
+            duration: micros(duration, duration_scale),
+            state,
+            volume: number(volume).map_or(-1.0, |v| v / 100.0),
+            shuffle: shuffle.parse().ok(),
+            repeat: match repeat {
+                "one" => RepeatMode::Track,
+                "all" | "true" => RepeatMode::List,
+                _ => RepeatMode::None,
+            },
+            media_type: MediaType::Music,
+            source_app_id: self.id.to_owned(),
+            ..Default::default()
+        };
+
+        if info.track_id.is_empty() {
+            info.track_id = info.metadata_track_id();
+        }
+        info.seek_end = info.duration;
+
+        Ok((info, micros(pos, 1_000_000.0)))
+    }
+
+    /// Current position in microseconds
+    pub fn position(&self) -> crate::Result<i64> {
+        let output = osascript(&format!(
+            r#"tell application "{}" to get player position"#,
+            self.app
+        ))?;
+
+        Ok(micros(&output, 1_000_000.0))
+    }
+
+    /// Run a single AppleScript `command` against the player
+    pub fn tell(&self, command: &str) -> crate::Result<()> {
+        osascript(&format!(r#"tell application "{}" to {command}"#, self.app)).map(drop)
+    }
+
+    /// Spotify has no `stop`
+    pub fn can_stop(&self) -> bool {
+        self.dialect == Dialect::Music
+    }
+
+    pub fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        match self.dialect {
+            Dialect::Music => self.tell(&format!("set shuffle enabled to {on}")),
+            Dialect::Spotify => self.tell(&format!("set shuffling to {on}")),
+        }
+    }
+
+    pub fn set_repeat(&self, mode: RepeatMode) -> crate::Result<()> {
+        match (self.dialect, mode) {
+            (Dialect::Music, RepeatMode::None) => self.tell("set song repeat to off"),
+            (Dialect::Music, RepeatMode::Track) => self.tell("set song repeat to one"),
+            (Dialect::Music, RepeatMode::List) => self.tell("set song repeat to all"),
+            (Dialect::Spotify, RepeatMode::None) => self.tell("set repeating to false"),
+            (Dialect::Spotify, RepeatMode::List) => self.tell("set repeating to true"),
+            (Dialect::Spotify, RepeatMode::Track) => Err(Error::unsupported(
+                "player does not support repeating a track",
+            )),
+        }
+    }
+}
+
+/// Microseconds as an AppleScript number of seconds
+pub fn seconds(micros: i64) -> String {
+    #[allow(clippy::cast_precision_loss, reason = "sub-microsecond loss is fine")]
+    let seconds = micros as f64 / 1_000_000.0;
+
+    format!("{seconds:.3}")
+}
+
+/// Numbers follow the user's locale, so the decimal separator may be a comma
+fn number(s: &str) -> Option<f64> {
+    s.trim().replace(',', ".").parse().ok()
+}
+
+fn micros(s: &str, scale: f64) -> i64 {
+    #[allow(clippy::cast_possible_truncation, reason = "rounded")]
+    number(s).map_or(0, |n| (n * scale).round() as i64)
+}
+
+fn osascript(script: &str) -> crate::Result<String> {
+    let output = Command::new("osascript").arg("-e").arg(script).output()?;
+
+    if !output.status.success() {
+        return Err(Error::new(String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spotify() {
+        let output = "playing\nSong\nArtist\nAlbum\nAlbum Artist\nspotify:track:1\n215000\n12,5\n80\nfalse\ntrue";
+        let (info, position) = SPOTIFY.parse(output).unwrap();
+
+        assert_eq!(info.title, "Song");
+        assert_eq!(info.track_id, "spotify:track:1");
+        assert_eq!(info.duration, 215_000_000);
+        assert_eq!(position, 12_500_000);
+        assert_eq!(info.shuffle, Some(false));
+        assert_eq!(info.repeat, RepeatMode::List);
+        assert!((info.volume - 0.8).abs() < f64::EPSILON);
+    }
+}
@@ -1,9 +1,19 @@
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos"), not(feature = "stub")))]
 mod unix;
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos"), not(feature = "stub")))]
 pub use unix::*;
 
-#[cfg(windows)]
+#[cfg(all(target_os = "macos", not(feature = "stub")))]
+mod macos;
+#[cfg(all(target_os = "macos", not(feature = "stub")))]
+pub use macos::*;
+
+#[cfg(all(windows, not(feature = "stub")))]
 mod windows;
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "stub")))]
 pub use windows::*;
+
+#[cfg(any(feature = "stub", not(any(unix, windows))))]
+mod stub;
+#[cfg(any(feature = "stub", not(any(unix, windows))))]
+pub use stub::*;
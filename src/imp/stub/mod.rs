@@ -0,0 +1,205 @@
+//! Fallback backend for platforms without a supported media session API.
+//!
+//! Never finds a session; every control returns an error.
+
+use crate::{
+    builder::Config, media_event::EventDispatcher, traits, Error, MediaInfo, PositionInfo,
+};
+
+fn unsupported() -> crate::Result<()> {
+    Err(Error::unsupported(
+        "media sessions are not supported on this platform",
+    ))
+}
+
+pub struct MediaSession {
+    config: Config,
+    events: EventDispatcher,
+    #[cfg(feature = "image")]
+    resize_cache: crate::cover::ResizeCache,
+}
+
+impl MediaSession {
+    pub fn new() -> crate::Result<Self> {
+        Self::with_config(Config::default())
+    }
+
+    pub(crate) fn with_config(config: Config) -> crate::Result<Self> {
+        tracing::info!("No media session backend for this platform");
+
+        Ok(Self {
+            events: EventDispatcher::with_history(config.track_history),
+            config,
+            #[cfg(feature = "image")]
+            resize_cache: crate::cover::ResizeCache::default(),
+        })
+    }
+
+    pub fn update(&mut self) {
+        let info = self.get_info();
+        self.events.dispatch(info, self.config.transition_log_level);
+    }
+
+    /// No-op: there is no position to resync
+    pub fn resync_position(&mut self) {}
+
+    /// Always `false`: there is no backend to control here
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        false
+    }
+
+    #[must_use]
+    pub fn get_capabilities(&self) -> crate::PlaybackCapabilities {
+        crate::PlaybackCapabilities::default()
+    }
+
+    /// Never woken: nothing ever changes
+    #[allow(clippy::unused_self)]
+    pub(crate) fn notifier(&self) -> crate::notify::Notify {
+        crate::notify::Notify::default()
+    }
+
+    /// Never: there are no players
+    #[allow(clippy::unused_self)]
+    pub(crate) fn session_appeared(&self) -> bool {
+        false
+    }
+
+    #[must_use]
+    #[allow(clippy::unused_self)]
+    pub fn list_sessions(&self) -> Vec<crate::SessionHandle> {
+        Vec::new()
+    }
+
+    /// Always fails: there are no sessions to pick from
+    pub fn select_session(&mut self, app_id: &str) -> crate::Result<()> {
+        Err(Error::no_active_session(format!("no session for {app_id}")))
+    }
+
+    pub fn unpin_session(&mut self) {}
+
+    /// No-op: there is nothing to release
+    #[allow(clippy::unused_self)]
+    pub(crate) fn close_impl(&mut self) {}
+
+    /// No-op: there are no covers here
+    #[allow(clippy::unused_self)]
+    pub(crate) fn set_fetch_cover_impl(&mut self, _fetch: bool) {}
+
+    #[allow(clippy::unused_self)]
+    pub(crate) fn session_states(&self) -> Vec<crate::PlaybackState> {
+        Vec::new()
+    }
+
+    /// Not enforced here
+    #[allow(clippy::unused_self)]
+    pub(crate) fn set_control_timeout_impl(&mut self, _timeout: std::time::Duration) {}
+
+    pub(crate) fn set_max_extrapolation_impl(&mut self, _max: std::time::Duration) {}
+
+    #[allow(clippy::unused_self)]
+    pub(crate) fn position_info(&self) -> PositionInfo {
+        PositionInfo::default()
+    }
+
+    #[allow(clippy::unused_self)]
+    pub(crate) fn current_info(&self) -> Option<&MediaInfo> {
+        None
+    }
+
+    pub(crate) fn events(&self) -> &EventDispatcher {
+        &self.events
+    }
+
+    pub(crate) fn events_mut(&mut self) -> &mut EventDispatcher {
+        &mut self.events
+    }
+
+    #[must_use]
+    pub fn get_info(&self) -> MediaInfo {
+        self.config.finalize(MediaInfo::default())
+    }
+
+    /// Stream the current cover into `writer`. There is never a cover here.
+    #[allow(clippy::unused_self)]
+    pub fn write_cover_to(&self, _writer: impl std::io::Write) -> crate::Result<()> {
+        Err(Error::new("no cover"))
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn resize_cache(&self) -> &crate::cover::ResizeCache {
+        &self.resize_cache
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl traits::MediaSessionControls for MediaSession {
+    fn next(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn pause(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn play(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn prev(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn stop(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn toggle_pause(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn set_position(&self, _position_micros: i64) -> crate::Result<()> {
+        unsupported()
+    }
+    fn seek_by(&self, _offset_micros: i64) -> crate::Result<()> {
+        unsupported()
+    }
+    fn set_volume(&self, _level: f64) -> crate::Result<()> {
+        unsupported()
+    }
+    fn set_shuffle(&self, _on: bool) -> crate::Result<()> {
+        unsupported()
+    }
+    fn set_repeat(&self, _mode: crate::RepeatMode) -> crate::Result<()> {
+        unsupported()
+    }
+    fn set_rate(&self, _rate: f64) -> crate::Result<()> {
+        unsupported()
+    }
+    fn supported_controls(&self) -> crate::ControlFlags {
+        crate::ControlFlags::empty()
+    }
+}
+
+impl traits::MediaSessionApp for MediaSession {
+    fn raise(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn quit(&self) -> crate::Result<()> {
+        unsupported()
+    }
+    fn can_raise(&self) -> bool {
+        false
+    }
+    fn can_quit(&self) -> bool {
+        false
+    }
+}
+
+impl traits::MediaSessionBackend for MediaSession {
+    fn backend_name(&self) -> &'static str {
+        "stub"
+    }
+    fn platform(&self) -> crate::Platform {
+        crate::Platform::Unsupported
+    }
+}
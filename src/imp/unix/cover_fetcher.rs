@@ -0,0 +1,115 @@
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Duration,
+};
+
+/// A downloaded cover: its URL, and its bytes unless the download failed
+type Fetched = (String, Option<Vec<u8>>);
+
+/// Downloads http(s) covers on a thread of its own, so `update` (and the streams
+/// polling it) never wait on the network.
+///
+/// The thread holds one `reqwest` client for every download and exits with the
+/// fetcher.
+pub struct CoverFetcher {
+    requests: Sender<String>,
+    results: Receiver<Fetched>,
+    /// URL of the download whose result is wanted; others are dropped as they land
+    pending: Option<String>,
+}
+
+impl CoverFetcher {
+    pub fn new(timeout: Duration) -> Self {
+        let (requests, urls) = channel::<String>();
+        let (done, results) = channel();
+
+        std::thread::spawn(move || {
+            // Built here: the blocking client can't be created within an async runtime
+            let client = reqwest::blocking::Client::builder()
+                .timeout(timeout)
+                .build()
+                .inspect_err(|e| tracing::warn!("Failed to create the cover client: {e}"))
+                .ok();
+
+            for url in urls {
+                let cover = client.as_ref().and_then(|client| download(client, &url));
+                if done.send((url, cover)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            results,
+            pending: None,
+        }
+    }
+
+    /// Download `url`, unless it is already on its way
+    pub fn request(&mut self, url: &str) {
+        if self.pending.as_deref() == Some(url) {
+            return;
+        }
+
+        tracing::info!("Downloading cover from: {url}");
+        _ = self.requests.send(url.to_owned());
+        self.pending = Some(url.to_owned());
+    }
+
+    /// Drop the result of the pending download, e.g. after a track change
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// The pending download, once it has landed
+    pub fn poll(&mut self) -> Option<Fetched> {
+        let fetched = self
+            .results
+            .try_iter()
+            .filter(|(url, _)| self.pending.as_ref() == Some(url))
+            .last()?;
+
+        self.pending = None;
+        Some(fetched)
+    }
+}
+
+fn download(client: &reqwest::blocking::Client, url: &str) -> Option<Vec<u8>> {
+    let fetch = || -> reqwest::Result<Vec<u8>> {
+        let response = client.get(url).send()?.error_for_status()?;
+        Ok(response.bytes()?.to_vec())
+    };
+
+    fetch()
+        .inspect(|cover| tracing::info!("Downloaded cover; size: {} Bytes", cover.len()))
+        .inspect_err(|e| tracing::warn!("Failed to download cover: {e}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_superseded_download_is_dropped() {
+        // Nothing listens on the discard port, so both fail right away
+        let mut fetcher = CoverFetcher::new(Duration::from_secs(1));
+        fetcher.request("http://127.0.0.1:9/old.png");
+        fetcher.request("http://127.0.0.1:9/new.png");
+
+        let started = Instant::now();
+        let fetched = loop {
+            if let Some(fetched) = fetcher.poll() {
+                break fetched;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        assert_eq!(fetched, (String::from("http://127.0.0.1:9/new.png"), None));
+        assert!(fetcher.poll().is_none());
+    }
+}
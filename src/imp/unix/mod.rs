@@ -1,17 +1,39 @@
-use std::{fs, time::Duration};
+use std::{
+    cell::OnceCell,
+    collections::HashSet,
+    fs,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use base64::{display::Base64Display, engine::general_purpose::STANDARD as BASE64_STANDARD};
 use dbus::{
     arg::{PropMap, RefArg},
     blocking,
-    blocking::stdintf::org_freedesktop_dbus::Properties as _,
+    blocking::stdintf::org_freedesktop_dbus::{Properties as _, PropertiesPropertiesChanged},
+    channel::Token,
     strings::BusName,
     Path,
 };
 
-use crate::{traits, MediaInfo};
+#[cfg(feature = "remote-art")]
+mod cover_fetcher;
+
+use crate::{
+    builder::Config,
+    media_event::EventDispatcher,
+    traits,
+    utils::{fnv1a_64, image_mime, micros_since_epoch},
+    Error, MediaInfo, MediaType, PlaybackCapabilities, PlaybackState, PositionInfo, RepeatMode,
+    SessionHandle,
+};
+
+type Proxy<'p> = blocking::Proxy<'p, Arc<blocking::SyncConnection>>;
 
-type Proxy<'p> = blocking::Proxy<'p, Box<blocking::Connection>>;
+/// Names of player properties changed since the last `update`, collected from
+/// `PropertiesChanged` signals
+type Changes = Arc<Mutex<HashSet<String>>>;
 
 const DBUS_DEST: &str = "org.freedesktop.DBus";
 const DBUS_PATH: &str = "/"; // "/org/freedesktop/DBus"
@@ -20,26 +42,40 @@ const PLAYER_PATH: &str = "/org/mpris/MediaPlayer2";
 
 const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2";
 const PLAYER_INTERFACE_PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+const PLAYER_INTERFACE_TRACKLIST: &str = "org.mpris.MediaPlayer2.TrackList";
+
+/// `mpris:trackid` meaning "no track"
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
 
 const TIMEOUT: Duration = Duration::new(5, 0);
 
-fn get_player_names(proxy: &Proxy) -> Vec<String> {
-    let res: (Vec<String>,) = proxy.method_call(DBUS_DEST, "ListNames", ()).unwrap();
-    res.0
-}
+/// 100 hours: longer lengths and positions come from players using another unit
+/// (e.g. older mpv builds sending nanoseconds)
+const MAX_PLAUSIBLE_MICROS: i64 = 100 * 60 * 60 * 1_000_000;
 
-fn select_player(proxy: &Proxy) -> Option<String> {
-    let names = get_player_names(proxy);
+/// How often streams and `wait_for_session` re-check the player, as signals are only
+/// read during `update`
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
+fn get_player_names(proxy: &Proxy) -> Result<Vec<String>, dbus::Error> {
+    let res: (Vec<String>,) = proxy.method_call(DBUS_DEST, "ListNames", ())?;
+    Ok(res.0)
+}
+
+/// Pick a player by `config` priority, then playing state, then `current`, then bus order
+fn select_player(
+    connection: &Arc<blocking::SyncConnection>,
+    names: &[String],
+    config: &Config,
+    current: Option<&str>,
+) -> Option<String> {
     let players: Vec<String> = names
         .iter()
         .filter(|s| s.starts_with(PLAYER_INTERFACE))
         .cloned()
         .collect();
 
-    if players.is_empty() {
-        return None;
-    }
+    let best_rank = players.iter().map(|p| config.priority_rank(p)).min()?;
 
     let count = players.len();
 
@@ -50,173 +86,709 @@ fn select_player(proxy: &Proxy) -> Option<String> {
             .enumerate()
             .for_each(|(i, p)| tracing::info!("  {i}) {p}"));
     }
-    tracing::info!("Selected: {}", players[0]);
-    Some(players[0].clone())
+
+    let mut candidates = players
+        .iter()
+        .filter(|p| config.priority_rank(p) == best_rank)
+        .peekable();
+
+    let first = candidates.next()?;
+    let selected = if candidates.peek().is_none() {
+        first
+    } else {
+        std::iter::once(first).chain(candidates).min_by_key(|p| {
+            (
+                !is_playing(connection, p),
+                current != Some(String::as_str(p)),
+            )
+        })?
+    };
+
+    tracing::info!("Selected: {selected}");
+    Some(selected.clone())
+}
+
+fn is_playing(connection: &Arc<blocking::SyncConnection>, dest: &str) -> bool {
+    get_proxy(connection, dest, PLAYER_PATH)
+        .get::<String>(PLAYER_INTERFACE_PLAYER, "PlaybackStatus")
+        .is_ok_and(|status| playback_state(&status) == PlaybackState::Playing)
 }
 
-fn get_proxy<'p, D, P>(dest: D, path: P) -> Proxy<'p>
+fn get_proxy<'p, D, P>(connection: &Arc<blocking::SyncConnection>, dest: D, path: P) -> Proxy<'p>
 where
     D: Into<BusName<'p>>,
     P: Into<Path<'p>>,
 {
-    let connection = Box::new(blocking::Connection::new_session().unwrap());
-
-    blocking::Proxy::<'p, Box<blocking::Connection>> {
+    blocking::Proxy {
         destination: dest.into(),
         path: path.into(),
         timeout: TIMEOUT,
-        connection,
+        connection: Arc::clone(connection),
+    }
+}
+
+fn get_dbus_proxy<'p>(connection: &Arc<blocking::SyncConnection>) -> Proxy<'p> {
+    get_proxy(connection, DBUS_DEST, DBUS_PATH)
+}
+
+/// A session bus connection for [`MediaSession::with_connection`], so several sessions
+/// (or the rest of the app, with the `dbus-interop` feature) use one socket.
+///
+/// Cheap to clone: clones share the connection. Linux only.
+#[derive(Clone)]
+pub struct DbusConnection(Arc<blocking::SyncConnection>);
+
+impl DbusConnection {
+    /// Connect to the session bus
+    pub fn session() -> crate::Result<Self> {
+        Ok(Self(Arc::new(blocking::SyncConnection::new_session()?)))
+    }
+
+    /// The underlying `dbus` connection, to use it for other calls too.
+    ///
+    /// Tied to the `dbus` version this crate is built with, so only with the
+    /// `dbus-interop` feature.
+    #[cfg(feature = "dbus-interop")]
+    #[must_use]
+    pub fn as_dbus(&self) -> &Arc<blocking::SyncConnection> {
+        &self.0
     }
 }
 
-fn get_dbus_proxy<'p>() -> Proxy<'p> {
-    get_proxy(DBUS_DEST, DBUS_PATH)
+#[cfg(feature = "dbus-interop")]
+impl From<Arc<blocking::SyncConnection>> for DbusConnection {
+    fn from(connection: Arc<blocking::SyncConnection>) -> Self {
+        Self(connection)
+    }
 }
 
 #[derive(Default)]
 pub struct MediaSession {
-    player: Option<blocking::Proxy<'static, Box<blocking::Connection>>>,
+    config: Config,
+    events: EventDispatcher,
+    /// The session bus, shared by every proxy; opened on first use unless given to
+    /// `with_connection`
+    connection: OnceCell<Arc<blocking::SyncConnection>>,
+    player: Option<Proxy<'static>>,
+    /// Changes announced by `player`; `None` if subscribing failed, so every `update`
+    /// re-reads everything
+    changes: Option<Changes>,
+    /// Match rule behind `changes`, removed with the player since the connection may
+    /// outlive it
+    changes_token: Option<Token>,
+    /// Bus name set by `select_session`, preferred whenever it is on the bus
+    pinned: Option<String>,
     media_info: Option<MediaInfo>,
     prev_cover_url: Option<String>,
     prev_cover_raw: Option<Vec<u8>>,
     prev_cover_b64: Option<String>,
+    prev_cover_hash: u64,
+    /// Started on the first remote cover
+    #[cfg(feature = "remote-art")]
+    cover_fetcher: Option<cover_fetcher::CoverFetcher>,
+    /// Set by `close`: the player is gone for good and `update` does nothing
+    closed: bool,
+    #[cfg(feature = "image")]
+    resize_cache: crate::cover::ResizeCache,
 }
 
 impl MediaSession {
-    #[must_use]
-    pub fn new() -> Self {
-        let player = Self::try_get_player_dest().map_or_else(
-            || {
+    /// Fails when the DBus session bus is unreachable
+    pub fn new() -> crate::Result<Self> {
+        Self::with_config(Config::default())
+    }
+
+    /// Use `connection` to talk to the players instead of opening a session bus
+    /// connection of our own, e.g. to share one between sessions.
+    ///
+    /// `update` processes the messages pending on it, so its other match callbacks may
+    /// run there.
+    pub fn with_connection(connection: DbusConnection) -> crate::Result<Self> {
+        Self::with_config_and_connection(Config::default(), OnceCell::from(connection.0))
+    }
+
+    pub(crate) fn with_config(config: Config) -> crate::Result<Self> {
+        Self::with_config_and_connection(config, OnceCell::new())
+    }
+
+    fn with_config_and_connection(
+        config: Config,
+        connection: OnceCell<Arc<blocking::SyncConnection>>,
+    ) -> crate::Result<Self> {
+        // No struct update syntax: `Self` is `Drop`
+        let mut session = Self::default();
+        session.events = EventDispatcher::with_history(config.track_history);
+        session.config = config;
+        session.connection = connection;
+
+        let player = match session.try_get_player_dest(None)? {
+            Some(player_dest) => Some(session.connect(player_dest)?),
+            None => {
                 tracing::info!("No players found");
                 None
-            },
-            |player_dest| {
-                let player = get_proxy(player_dest, PLAYER_PATH);
-                Some(player)
-            },
-        );
+            }
+        };
+        session.set_player(player);
 
-        Self {
-            player,
-            ..Default::default()
+        Ok(session)
+    }
+
+    /// The session bus, connecting on first use
+    fn connection(&self) -> Result<&Arc<blocking::SyncConnection>, dbus::Error> {
+        if let Some(connection) = self.connection.get() {
+            return Ok(connection);
         }
+
+        let connection = Arc::new(blocking::SyncConnection::new_session()?);
+        Ok(self.connection.get_or_init(|| connection))
     }
 
-    fn try_get_player_dest() -> Option<String> {
-        let dbus_proxy = get_dbus_proxy();
+    /// Switch to `player`, dropping what was read from the previous one
+    fn set_player(&mut self, player: Option<Proxy<'static>>) {
+        if let (Some(old), Some(token)) = (&self.player, self.changes_token.take()) {
+            if let Err(e) = old.connection.remove_match(token) {
+                tracing::debug!("Failed to unsubscribe from player changes: {e}");
+            }
+        }
+
+        (self.changes_token, self.changes) = player.as_ref().and_then(subscribe).unzip();
+        self.player = player;
+        self.media_info = None;
+    }
 
-        select_player(&dbus_proxy)
+    /// Proxy for the player at `dest`, with the control timeout
+    fn connect(&self, dest: String) -> Result<Proxy<'static>, dbus::Error> {
+        let mut player = get_proxy(self.connection()?, dest, PLAYER_PATH);
+        player.timeout = self.config.control_timeout;
+        Ok(player)
+    }
+
+    pub(crate) fn set_control_timeout_impl(&mut self, timeout: Duration) {
+        self.config.control_timeout = timeout;
+
+        if let Some(player) = &mut self.player {
+            player.timeout = timeout;
+        }
+    }
+
+    pub(crate) fn set_max_extrapolation_impl(&mut self, max: Duration) {
+        self.config.max_extrapolation = max;
+    }
+
+    pub(crate) fn set_fetch_cover_impl(&mut self, fetch: bool) {
+        self.config.fetch_cover = fetch;
+
+        self.prev_cover_url = None;
+        self.prev_cover_raw = None;
+        self.prev_cover_b64 = None;
+        self.cancel_cover_fetch();
+
+        // Re-read the track (without the signals, every update does)
+        if let Some(changes) = &self.changes {
+            changes.lock().unwrap().insert(String::from("Metadata"));
+        }
+    }
+
+    fn try_get_player_dest(&self, current: Option<&str>) -> Result<Option<String>, dbus::Error> {
+        let connection = self.connection()?;
+        let names = get_player_names(&get_dbus_proxy(connection))?;
+
+        if let Some(pinned) = self.pinned.as_ref().filter(|p| names.contains(p)) {
+            return Ok(Some(pinned.clone()));
+        }
+
+        Ok(select_player(connection, &names, &self.config, current))
+    }
+
+    /// Pin the session to the player with bus name `app_id` (e.g.
+    /// `org.mpris.MediaPlayer2.spotify`), ignoring priority.
+    ///
+    /// While the player is gone the usual selection applies; it is picked again when
+    /// it returns. Fails if no such player is on the bus now.
+    pub fn select_session(&mut self, app_id: &str) -> crate::Result<()> {
+        if self.closed {
+            return Err(Error::no_active_session("the session is closed"));
+        }
+
+        let names = get_player_names(&get_dbus_proxy(self.connection()?))?;
+
+        if !names.iter().any(|name| name == app_id) {
+            return Err(Error::no_active_session(format!("no session for {app_id}")));
+        }
+
+        self.pinned = Some(app_id.to_owned());
+        self.update_player();
+
+        Ok(())
+    }
+
+    /// Undo [`select_session`](Self::select_session)
+    pub fn unpin_session(&mut self) {
+        self.pinned = None;
+        self.update_player();
     }
 
     fn update_player(&mut self) {
+        if self.closed {
+            return;
+        }
+
         // Check for player change
-        let new_dest = Self::try_get_player_dest();
         let cur_dest = self.player.as_ref().map(|p| p.destination.to_string());
+        let new_dest = match self.try_get_player_dest(cur_dest.as_deref()) {
+            Ok(dest) => dest,
+            Err(e) => {
+                tracing::warn!("Failed to list players: {e}");
+                return;
+            }
+        };
 
-        if new_dest != cur_dest {
-            if let Some(dest) = new_dest {
-                self.player = Some(get_proxy(dest, PLAYER_PATH));
+        if new_dest == cur_dest {
+            return;
+        }
+
+        match new_dest {
+            Some(dest) => match self.connect(dest.clone()) {
+                Ok(player) => {
+                    self.set_player(Some(player));
+                    self.events.session_changed(Some(dest));
+                }
+                Err(e) => tracing::warn!("Failed to connect to player: {e}"),
+            },
+            None => {
+                self.set_player(None);
+                self.events.session_changed(None);
             }
         }
     }
 
     fn update_info(&mut self) {
-        if let Some(player) = &self.player {
-            // Error on player application close
-            let metadata: Result<PropMap, dbus::Error> =
-                player.get(PLAYER_INTERFACE_PLAYER, "Metadata");
+        let Some(player) = &self.player else {
+            return;
+        };
 
-            if metadata.is_err() {
-                self.media_info = None;
+        // Only what the player announced changed, unless it is a new track
+        let changed = self
+            .changes
+            .as_ref()
+            .and_then(|changes| take_changes(player, changes));
+        if let (Some(info), Some(changed)) = (&mut self.media_info, changed) {
+            if !changed.contains("Metadata") {
+                for name in &changed {
+                    read_property(player, info, name);
+                }
+                if let Err(e) = read_position(player, info) {
+                    self.drop_player(&e);
+                }
                 return;
             }
+        }
 
-            let metadata: PropMap = metadata.unwrap();
+        let (mut info, cover_url) = match read_info(player) {
+            Ok(read) => read,
+            Err(e) if is_gone(&e) => {
+                self.drop_player(&e);
+                return;
+            }
+            Err(_) => {
+                self.media_info = None;
+                return;
+            }
+        };
 
-            let position: Result<i64, dbus::Error> =
-                player.get(PLAYER_INTERFACE_PLAYER, "Position");
+        let cover_url = cover_url.filter(|_| self.config.fetch_cover);
+        if !cover_url.as_deref().is_some_and(is_remote) {
+            self.cancel_cover_fetch();
+        }
 
-            let state: Result<String, dbus::Error> =
-                player.get(PLAYER_INTERFACE_PLAYER, "PlaybackStatus");
+        let cover_raw = cover_url.and_then(|url| {
+            tracing::info!("Cover url: {url}");
+            self.get_cover_raw(&url)
+        });
+        if let Some(cover_raw) = cover_raw {
+            let cover_b64 = self.prev_cover_b64.clone().unwrap_or_default();
+            set_cover(&mut info, cover_raw, self.prev_cover_hash, cover_b64);
+        }
 
-            let (cover_raw, cover_b64) = get_string(&metadata, "mpris:artUrl")
-                .filter(|url| !url.is_empty())
-                .map_or((None, None), |url| {
-                    tracing::info!("Cover url: {url}");
-                    let cover_url = url.strip_prefix("file://").unwrap().to_string();
-                    // cover_raw = self.get_cover_raw(cover_url.clone());
-                    let cover_raw = None;
-                    let cover_b64 = self.get_cover_b64(cover_url);
+        self.media_info = Some(info);
+    }
 
-                    (cover_raw, cover_b64)
-                });
+    /// Forget a player that crashed or hung, so the next `update` picks one afresh
+    /// (the same one, if it restarted)
+    fn drop_player(&mut self, error: &dbus::Error) {
+        tracing::warn!("Lost the player: {error}");
 
-            self.media_info = Some(MediaInfo {
-                title: get_string(&metadata, "xesam:title").unwrap_or_default(),
-                artist: get_first_string(&metadata, "xesam:artist").unwrap_or_default(),
-                duration: get_i64(&metadata, "mpris:length").unwrap_or_default(),
-                position: position.unwrap_or_default(),
-                state: state.map(|s| s.to_lowercase()).unwrap_or_default(),
-                cover_raw: cover_raw.unwrap_or_default(),
-                cover_b64: cover_b64.unwrap_or_else(|| String::from("Missing")),
-                album_title: get_string(&metadata, "xesam:albumArtist").unwrap_or_default(),
-                album_artist: get_string(&metadata, "xesam:album").unwrap_or_default(),
-            });
-        }
+        self.set_player(None);
+        self.events.session_changed(None);
     }
 
     pub fn update(&mut self) {
+        if self.closed {
+            return;
+        }
+
         self.update_player();
         self.update_info();
+        #[cfg(feature = "remote-art")]
+        self.apply_fetched_cover();
+
+        let info = self.get_info();
+        self.events.dispatch(info, self.config.transition_log_level);
+    }
+
+    pub(crate) fn close_impl(&mut self) {
+        if self.player.is_some() {
+            self.set_player(None);
+            self.events.session_changed(None);
+        }
+
+        self.media_info = None;
+        self.prev_cover_url = None;
+        self.prev_cover_raw = None;
+        self.prev_cover_b64 = None;
+        self.cancel_cover_fetch();
+        self.closed = true;
+    }
+
+    /// Every MPRIS player on the bus, the selected one included.
+    ///
+    /// Other players are read on the spot; their covers are only loaded from local files.
+    #[must_use]
+    pub fn list_sessions(&self) -> Vec<SessionHandle> {
+        let connection = match self.connection() {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::warn!("Failed to list players: {e}");
+                return Vec::new();
+            }
+        };
+        let names = match get_player_names(&get_dbus_proxy(connection)) {
+            Ok(names) => names,
+            Err(e) => {
+                tracing::warn!("Failed to list players: {e}");
+                return Vec::new();
+            }
+        };
+
+        names
+            .into_iter()
+            .filter(|name| name.starts_with(PLAYER_INTERFACE))
+            .filter_map(|name| {
+                let is_selected = self
+                    .player
+                    .as_ref()
+                    .is_some_and(|p| *p.destination == *name);
+
+                let info = if is_selected {
+                    self.get_info()
+                } else {
+                    let player = get_proxy(connection, name.clone(), PLAYER_PATH);
+                    let (mut info, cover_url) = read_info(&player).ok()?;
+
+                    if let Some(raw) = cover_url
+                        .filter(|url| self.config.fetch_cover && !is_remote(url))
+                        .and_then(|url| read_cover(cover_path(&url)))
+                    {
+                        let hash = fnv1a_64(&raw);
+                        let b64 = Base64Display::new(&raw, &BASE64_STANDARD).to_string();
+                        set_cover(&mut info, raw, hash, b64);
+                    }
+
+                    self.config.finalize(info)
+                };
+
+                Some(SessionHandle {
+                    source_app_id: name,
+                    info,
+                })
+            })
+            .collect()
+    }
+
+    /// Playback state of every player on the bus
+    pub(crate) fn session_states(&self) -> Vec<PlaybackState> {
+        let Ok(connection) = self.connection() else {
+            return Vec::new();
+        };
+        let Ok(names) = get_player_names(&get_dbus_proxy(connection)) else {
+            return Vec::new();
+        };
+
+        names
+            .iter()
+            .filter(|name| name.starts_with(PLAYER_INTERFACE))
+            .filter_map(|name| {
+                get_proxy(connection, name.as_str(), PLAYER_PATH)
+                    .get::<String>(PLAYER_INTERFACE_PLAYER, "PlaybackStatus")
+                    .ok()
+            })
+            .map(|status| playback_state(&status))
+            .collect()
+    }
+
+    /// Re-read the position from the player.
+    ///
+    /// MPRIS positions are read on every `update`, so this only matters when the app
+    /// wants a fresh value right away, e.g. on resume from sleep.
+    pub fn resync_position(&mut self) {
+        let (Some(player), Some(info)) = (&self.player, &mut self.media_info) else {
+            return;
+        };
+
+        if let Err(e) = read_position(player, info) {
+            self.drop_player(&e);
+        }
+    }
+
+    /// Woken every [`STREAM_POLL_INTERVAL`] until the stream holding it is dropped
+    #[allow(clippy::unused_self)]
+    pub(crate) fn notifier(&self) -> crate::notify::Notify {
+        crate::notify::Notify::ticker(STREAM_POLL_INTERVAL)
+    }
+
+    /// Whether there is a player to read, from the bus names alone
+    pub(crate) fn session_appeared(&self) -> bool {
+        self.player.is_some() || matches!(self.try_get_player_dest(None), Ok(Some(_)))
+    }
+
+    /// Positions are read on `update`, so they are anchored at the read
+    pub(crate) fn position_info(&self) -> PositionInfo {
+        self.media_info
+            .as_ref()
+            .map_or_else(PositionInfo::default, |info| PositionInfo {
+                pos_raw: info.position,
+                pos_last_update: info.last_updated,
+                playback_rate: info.playback_rate,
+                ..self.config.position_info()
+            })
+    }
+
+    pub(crate) fn current_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref()
+    }
+
+    pub(crate) fn events(&self) -> &EventDispatcher {
+        &self.events
+    }
+
+    pub(crate) fn events_mut(&mut self) -> &mut EventDispatcher {
+        &mut self.events
     }
 
     #[must_use]
     pub fn get_info(&self) -> MediaInfo {
-        self.media_info.clone().unwrap_or_default()
+        self.config
+            .finalize(self.media_info.clone().unwrap_or_default())
     }
 
-    fn get_cover_raw(&mut self, cover_url: impl AsRef<str>) -> Option<Vec<u8>> {
-        if let Some(prev_url) = &self.prev_cover_url {
-            if *prev_url == cover_url.as_ref() {
-                return self.prev_cover_raw.clone();
-            }
+    /// Whether there is a player to control; controls are no-ops (`Ok(())`) otherwise
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        self.player.is_some()
+    }
+
+    #[must_use]
+    pub fn get_capabilities(&self) -> PlaybackCapabilities {
+        let Some(player) = &self.player else {
+            return PlaybackCapabilities::default();
+        };
+
+        let flag = |name: &str| {
+            player
+                .get::<bool>(PLAYER_INTERFACE_PLAYER, name)
+                .unwrap_or(false)
+        };
+
+        // Without CanControl every other Can* is meaningless
+        if !flag("CanControl") {
+            return PlaybackCapabilities::default();
         }
 
-        self.prev_cover_url = Some(cover_url.as_ref().to_owned());
+        PlaybackCapabilities {
+            can_play: flag("CanPlay"),
+            can_pause: flag("CanPause"),
+            can_stop: true,
+            can_next: flag("CanGoNext"),
+            can_prev: flag("CanGoPrevious"),
+            can_seek: flag("CanSeek"),
+            // Optional properties, present only when supported
+            can_shuffle: player
+                .get::<bool>(PLAYER_INTERFACE_PLAYER, "Shuffle")
+                .is_ok(),
+            can_repeat: player
+                .get::<String>(PLAYER_INTERFACE_PLAYER, "LoopStatus")
+                .is_ok(),
+            can_set_rate: {
+                let (min, max) = rate_range(player);
+                max > min
+            },
+            can_set_volume: player.get::<f64>(PLAYER_INTERFACE_PLAYER, "Volume").is_ok(),
+        }
+    }
+
+    /// Every raw metadata key/value the current player exposes, one per line.
+    ///
+    /// Meant for bug reports about missing or wrong fields.
+    #[must_use]
+    pub fn dump_metadata(&self) -> String {
+        let Some(player) = &self.player else {
+            return String::from("No player");
+        };
 
-        tracing::info!("Reading cover at: {}", cover_url.as_ref());
+        let metadata: PropMap = match player.get(PLAYER_INTERFACE_PLAYER, "Metadata") {
+            Ok(metadata) => metadata,
+            Err(e) => return format!("{}: failed to read metadata: {e}", player.destination),
+        };
 
-        let cover_raw = fs::read(cover_url.as_ref())
-            .inspect(|cover| tracing::info!("Read cover; size: {} Bytes", cover.len()))
-            .inspect_err(|e| tracing::error!("Failed to read cover: {e}"))
-            .ok();
+        let mut entries: Vec<_> = metadata.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
 
-        self.prev_cover_raw.clone_from(&cover_raw);
+        let mut dump = player.destination.to_string();
+        for (key, value) in entries {
+            dump += &format!("\n  {key}: {:?}", value.0);
+        }
 
-        cover_raw
+        dump
     }
 
-    fn get_cover_b64(&mut self, cover_url: impl AsRef<str>) -> Option<String> {
-        if let Some(prev_url) = &self.prev_cover_url {
-            if *prev_url == cover_url.as_ref() {
-                return self.prev_cover_b64.clone();
-            }
+    /// Stream the current cover file into `writer`, without buffering the whole image
+    pub fn write_cover_to(&self, mut writer: impl Write) -> crate::Result<()> {
+        let player = self
+            .player
+            .as_ref()
+            .ok_or_else(|| Error::no_active_session("no active session"))?;
+
+        let metadata: PropMap = player.get(PLAYER_INTERFACE_PLAYER, "Metadata")?;
+        let url = get_string(&metadata, "mpris:artUrl")
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| Error::new("no cover"))?;
+
+        // Remote covers only exist as the cached download
+        if is_remote(&url) {
+            let raw = self
+                .prev_cover_raw
+                .as_ref()
+                .filter(|_| self.prev_cover_url.as_deref() == Some(url.as_str()))
+                .ok_or_else(|| Error::new("remote cover not downloaded"))?;
+            writer.write_all(raw)?;
+            writer.flush()?;
+
+            return Ok(());
+        }
+
+        let mut file = fs::File::open(cover_path(&url))?;
+        std::io::copy(&mut file, &mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn resize_cache(&self) -> &crate::cover::ResizeCache {
+        &self.resize_cache
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Load the cover behind `cover_url`, caching its bytes, hash and b64 per URL.
+    ///
+    /// Remote covers are downloaded in the background: `None` until a later `update`
+    /// finds them downloaded.
+    fn get_cover_raw(&mut self, cover_url: &str) -> Option<Vec<u8>> {
+        let remote = is_remote(cover_url);
+
+        // Local failures aren't cached: players may write the file after announcing it.
+        // Remote ones are, so an unreachable URL isn't re-requested every update.
+        if self.prev_cover_url.as_deref() == Some(cover_url)
+            && (remote || self.prev_cover_raw.is_some())
+        {
+            return self.prev_cover_raw.clone();
         }
 
-        self.prev_cover_url = Some(cover_url.as_ref().to_owned());
+        if remote {
+            self.fetch_cover(cover_url);
+            return None;
+        }
+
+        let cover_raw = read_cover(cover_path(cover_url));
+        self.cache_cover(cover_url.to_owned(), cover_raw.clone());
+
+        cover_raw
+    }
+
+    fn cache_cover(&mut self, cover_url: String, cover_raw: Option<Vec<u8>>) {
+        self.prev_cover_url = Some(cover_url);
+        self.prev_cover_hash = cover_raw.as_deref().map_or(0, fnv1a_64);
+        self.prev_cover_b64 = cover_raw
+            .as_ref()
+            .map(|raw| Base64Display::new(raw, &BASE64_STANDARD).to_string());
+        self.prev_cover_raw = cover_raw;
+    }
+
+    #[cfg(feature = "remote-art")]
+    fn fetch_cover(&mut self, cover_url: &str) {
+        let timeout = self.config.thumbnail_timeout;
+        self.cover_fetcher
+            .get_or_insert_with(|| cover_fetcher::CoverFetcher::new(timeout))
+            .request(cover_url);
+    }
+
+    #[cfg(not(feature = "remote-art"))]
+    #[allow(clippy::unused_self)]
+    fn fetch_cover(&self, cover_url: &str) {
+        tracing::debug!("Skipping remote cover (`remote-art` feature is off): {cover_url}");
+    }
 
-        let cover_b64 = fs::read(cover_url.as_ref())
-            .inspect(|_| tracing::info!("B64 cover read success"))
-            .inspect_err(|e| tracing::warn!("Failed to read file for b64: {e}"))
-            .map(|raw| Base64Display::new(&raw, &BASE64_STANDARD).to_string())
-            .ok();
+    /// Forget the cover being downloaded, which no longer belongs to the track
+    #[cfg_attr(not(feature = "remote-art"), allow(clippy::unused_self))]
+    fn cancel_cover_fetch(&mut self) {
+        #[cfg(feature = "remote-art")]
+        if let Some(fetcher) = &mut self.cover_fetcher {
+            fetcher.cancel();
+        }
+    }
 
-        self.prev_cover_b64.clone_from(&cover_b64);
+    /// Cache a finished download and show it on the track that requested it
+    #[cfg(feature = "remote-art")]
+    fn apply_fetched_cover(&mut self) {
+        let Some((cover_url, cover_raw)) = self
+            .cover_fetcher
+            .as_mut()
+            .and_then(cover_fetcher::CoverFetcher::poll)
+        else {
+            return;
+        };
 
-        cover_b64
+        self.cache_cover(cover_url, cover_raw.clone());
+        if let (Some(info), Some(cover_raw)) = (&mut self.media_info, cover_raw) {
+            let cover_b64 = self.prev_cover_b64.clone().unwrap_or_default();
+            set_cover(info, cover_raw, self.prev_cover_hash, cover_b64);
+        }
     }
 }
 
+fn is_remote(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn read_cover(cover_path: &str) -> Option<Vec<u8>> {
+    tracing::info!("Reading cover at: {cover_path}");
+
+    // Debug level, as a missing file is retried on every update
+    fs::read(cover_path)
+        .inspect(|cover| tracing::info!("Read cover; size: {} Bytes", cover.len()))
+        .inspect_err(|e| tracing::debug!("Failed to read cover: {e}"))
+        .ok()
+}
+
+/// `artUrl` is a `file://` URL, though some players send a bare path
+fn cover_path(url: &str) -> &str {
+    url.strip_prefix("file://").unwrap_or(url)
+}
+
 fn action(player_opt: Option<&Proxy>, command: &str) -> crate::Result<()> {
     if let Some(player) = player_opt {
         return player
@@ -227,6 +799,33 @@ fn action(player_opt: Option<&Proxy>, command: &str) -> crate::Result<()> {
     Ok(())
 }
 
+/// Call `command` on the root interface if the player advertises `capability`
+fn app_action(player_opt: Option<&Proxy>, command: &str, capability: &str) -> crate::Result<()> {
+    let Some(player) = player_opt else {
+        return Ok(());
+    };
+
+    let capable: bool = player.get(PLAYER_INTERFACE, capability)?;
+    if !capable {
+        return Err(Error::unsupported(format!(
+            "player does not support {command}"
+        )));
+    }
+
+    player
+        .method_call(PLAYER_INTERFACE, command, ())
+        .map_err(crate::error::Error::from)
+}
+
+fn ensure_can_seek(player: &Proxy) -> crate::Result<()> {
+    let can_seek: bool = player.get(PLAYER_INTERFACE_PLAYER, "CanSeek")?;
+    if !can_seek {
+        return Err(Error::unsupported("player does not support seeking"));
+    }
+
+    Ok(())
+}
+
 impl traits::MediaSessionControls for MediaSession {
     fn next(&self) -> crate::Result<()> {
         action(self.player.as_ref(), "Next")
@@ -246,20 +845,404 @@ impl traits::MediaSessionControls for MediaSession {
     fn toggle_pause(&self) -> crate::Result<()> {
         action(self.player.as_ref(), "PlayPause")
     }
+    fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        ensure_can_seek(player)?;
+
+        // SetPosition is ignored unless it names the current track
+        let metadata: PropMap = player.get(PLAYER_INTERFACE_PLAYER, "Metadata")?;
+        let track_id = get_string(&metadata, "mpris:trackid")
+            .ok_or_else(|| Error::new("player did not report a track id"))?;
+        let track_id = Path::new(track_id).map_err(Error::new)?;
+
+        player
+            .method_call(
+                PLAYER_INTERFACE_PLAYER,
+                "SetPosition",
+                (track_id, position_micros),
+            )
+            .map_err(Error::from)
+    }
+    fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        ensure_can_seek(player)?;
+
+        // Relative, so the player clamps it (past the end skips to the next track)
+        player
+            .method_call(PLAYER_INTERFACE_PLAYER, "Seek", (offset_micros,))
+            .map_err(Error::from)
+    }
+    fn set_volume(&self, level: f64) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        player
+            .set(PLAYER_INTERFACE_PLAYER, "Volume", level.clamp(0.0, 1.0))
+            .map_err(Error::from)
+    }
+    fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        // Optional property: players without shuffle don't expose it at all
+        if player
+            .get::<bool>(PLAYER_INTERFACE_PLAYER, "Shuffle")
+            .is_err()
+        {
+            return Err(Error::unsupported("player does not support shuffle"));
+        }
+
+        player
+            .set(PLAYER_INTERFACE_PLAYER, "Shuffle", on)
+            .map_err(Error::from)
+    }
+    fn set_repeat(&self, mode: RepeatMode) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        if player
+            .get::<String>(PLAYER_INTERFACE_PLAYER, "LoopStatus")
+            .is_err()
+        {
+            return Err(Error::unsupported("player does not support repeat"));
+        }
+
+        player
+            .set(PLAYER_INTERFACE_PLAYER, "LoopStatus", loop_status(mode))
+            .map_err(Error::from)
+    }
+    fn set_rate(&self, rate: f64) -> crate::Result<()> {
+        let Some(player) = &self.player else {
+            return Ok(());
+        };
+
+        let (min, max) = rate_range(player);
+        if max <= min {
+            return Err(Error::unsupported(
+                "player does not support changing the rate",
+            ));
+        }
+
+        player
+            .set(PLAYER_INTERFACE_PLAYER, "Rate", rate.clamp(min, max))
+            .map_err(Error::from)
+    }
+    fn supported_controls(&self) -> crate::ControlFlags {
+        self.get_capabilities().into()
+    }
+}
+
+impl Drop for MediaSession {
+    fn drop(&mut self) {
+        // Leave a shared connection without our match rule
+        self.set_player(None);
+    }
+}
+
+impl traits::MediaSessionApp for MediaSession {
+    fn raise(&self) -> crate::Result<()> {
+        app_action(self.player.as_ref(), "Raise", "CanRaise")
+    }
+    fn quit(&self) -> crate::Result<()> {
+        app_action(self.player.as_ref(), "Quit", "CanQuit")
+    }
+    fn can_raise(&self) -> bool {
+        self.player
+            .as_ref()
+            .and_then(|p| p.get(PLAYER_INTERFACE, "CanRaise").ok())
+            .unwrap_or(false)
+    }
+    fn can_quit(&self) -> bool {
+        self.player
+            .as_ref()
+            .and_then(|p| p.get(PLAYER_INTERFACE, "CanQuit").ok())
+            .unwrap_or(false)
+    }
+}
+
+impl traits::MediaSessionBackend for MediaSession {
+    fn backend_name(&self) -> &'static str {
+        "MPRIS (libdbus)"
+    }
+    fn platform(&self) -> crate::Platform {
+        crate::Platform::Linux
+    }
+}
+
+/// Player properties read into [`MediaInfo`] besides `Metadata` and `Position`
+/// (`Tracks` is the `TrackList` one)
+const PLAYER_PROPERTIES: [&str; 6] = [
+    "PlaybackStatus",
+    "Rate",
+    "Volume",
+    "Shuffle",
+    "LoopStatus",
+    "Tracks",
+];
+
+/// Record the names of properties `player` reports as changed
+fn subscribe(player: &Proxy) -> Option<(Token, Changes)> {
+    let changes = Changes::default();
+    let sink = Arc::clone(&changes);
+
+    let token = player
+        .match_signal(
+            move |signal: PropertiesPropertiesChanged,
+                  _: &blocking::SyncConnection,
+                  _: &dbus::Message| {
+                if signal.interface_name == PLAYER_INTERFACE_PLAYER
+                    || signal.interface_name == PLAYER_INTERFACE_TRACKLIST
+                {
+                    sink.lock().unwrap().extend(
+                        signal
+                            .changed_properties
+                            .into_keys()
+                            .chain(signal.invalidated_properties),
+                    );
+                }
+                true
+            },
+        )
+        .inspect_err(|e| tracing::warn!("Failed to subscribe to player changes: {e}"))
+        .ok()?;
+
+    Some((token, changes))
+}
+
+/// Names of the properties changed since the last call; `None` if the signals
+/// can't be read
+fn take_changes(player: &Proxy, changes: &Changes) -> Option<HashSet<String>> {
+    // Run the signal callbacks for everything queued
+    loop {
+        match player.connection.process(Duration::ZERO) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                tracing::warn!("Failed to read player signals: {e}");
+                return None;
+            }
+        }
+    }
+
+    Some(std::mem::take(&mut *changes.lock().unwrap()))
+}
+
+/// Re-read the player property `name` into `info`
+fn read_property(player: &Proxy, info: &mut MediaInfo, name: &str) {
+    match name {
+        "PlaybackStatus" => {
+            info.state = player
+                .get::<String>(PLAYER_INTERFACE_PLAYER, name)
+                .map(|status| playback_state(&status))
+                .unwrap_or_default();
+        }
+        "Rate" => info.playback_rate = player.get(PLAYER_INTERFACE_PLAYER, name).unwrap_or(1.0),
+        "Volume" => info.volume = player.get(PLAYER_INTERFACE_PLAYER, name).unwrap_or(-1.0),
+        "Shuffle" => info.shuffle = player.get(PLAYER_INTERFACE_PLAYER, name).ok(),
+        "Tracks" => info.queue_length = read_queue_length(player),
+        "LoopStatus" => {
+            info.repeat = player
+                .get::<String>(PLAYER_INTERFACE_PLAYER, name)
+                .map_or(RepeatMode::None, |status| repeat_mode(&status));
+        }
+        _ => {}
+    }
+}
+
+/// `MinimumRate` and `MaximumRate`, both `1.0` when absent (the rate is fixed)
+fn rate_range(player: &Proxy) -> (f64, f64) {
+    let min = player
+        .get(PLAYER_INTERFACE_PLAYER, "MinimumRate")
+        .unwrap_or(1.0);
+    let max = player
+        .get(PLAYER_INTERFACE_PLAYER, "MaximumRate")
+        .unwrap_or(1.0);
+
+    (min, max)
+}
+
+/// Length of the player's tracklist; `None` unless it implements `TrackList`
+fn read_queue_length(player: &Proxy) -> Option<u32> {
+    let has_track_list: bool = player.get(PLAYER_INTERFACE, "HasTrackList").ok()?;
+    if !has_track_list {
+        return None;
+    }
+
+    let tracks: Vec<Path> = player.get(PLAYER_INTERFACE_TRACKLIST, "Tracks").ok()?;
+    u32::try_from(tracks.len()).ok()
+}
+
+/// Positions aren't signalled (only seeks are), so this is read on every `update`.
+///
+/// Fails only if the player is gone; otherwise the position is just unknown.
+fn read_position(player: &Proxy, info: &mut MediaInfo) -> Result<(), dbus::Error> {
+    match player.get::<i64>(PLAYER_INTERFACE_PLAYER, "Position") {
+        Ok(position) if plausible_micros(position, "Position").is_some() => {
+            info.position = position;
+            info.last_updated = micros_since_epoch();
+        }
+        Err(e) if is_gone(&e) => return Err(e),
+        _ => {
+            info.position = 0;
+            info.last_updated = 0;
+        }
+    }
+
+    Ok(())
+}
+
+/// `value` if it's a sane time in microseconds, logging it otherwise
+fn plausible_micros(value: i64, name: &str) -> Option<i64> {
+    if (0..=MAX_PLAUSIBLE_MICROS).contains(&value) {
+        return Some(value);
+    }
+
+    tracing::warn!("Ignoring implausible {name} of {value}us, treating it as unknown");
+    None
+}
+
+/// The player process exited or stopped answering
+fn is_gone(error: &dbus::Error) -> bool {
+    matches!(
+        error.name(),
+        Some(
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                | "org.freedesktop.DBus.Error.NoReply"
+        )
+    )
+}
+
+/// Everything but the cover, plus the cover's URL
+fn read_info(player: &Proxy) -> Result<(MediaInfo, Option<String>), dbus::Error> {
+    let metadata: PropMap = player.get(PLAYER_INTERFACE_PLAYER, "Metadata")?;
+
+    let cover_url = get_string(&metadata, "mpris:artUrl").filter(|url| !url.is_empty());
+
+    // The cover fields stay empty until (and unless) the cover is loaded
+    let mut info = MediaInfo {
+        source_app_id: player.destination.to_string(),
+        ..info_from_metadata(&metadata)
+    };
+
+    read_position(player, &mut info)?;
+    for name in PLAYER_PROPERTIES {
+        read_property(player, &mut info, name);
+    }
+
+    Ok((info, cover_url))
+}
+
+fn set_cover(info: &mut MediaInfo, raw: Vec<u8>, hash: u64, b64: String) {
+    info.cover_mime = image_mime(&raw).to_owned();
+    info.cover_hash = hash;
+    info.cover_b64 = b64;
+    info.cover_raw = raw;
+}
+
+/// The track fields of [`MediaInfo`] found in MPRIS `Metadata`
+fn info_from_metadata(metadata: &PropMap) -> MediaInfo {
+    let artists = get_strings(metadata, "xesam:artist");
+
+    let mut info = MediaInfo {
+        title: get_string(metadata, "xesam:title").unwrap_or_default(),
+        artist: artists.join(", "),
+        artists,
+        album_title: get_string(metadata, "xesam:album").unwrap_or_default(),
+        album_artist: get_strings(metadata, "xesam:albumArtist").join(", "),
+        genres: get_strings(metadata, "xesam:genre"),
+        track_number: get_i64(metadata, "xesam:trackNumber").and_then(|n| u32::try_from(n).ok()),
+        rating: get_f64(metadata, "xesam:userRating").filter(|r| (0.0..=1.0).contains(r)),
+        // Unknown length is 0, making it live
+        duration: get_i64(metadata, "mpris:length")
+            .and_then(|length| plausible_micros(length, "mpris:length"))
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    info.is_live = info.has_unknown_length();
+    // MPRIS has no seek window
+    info.seek_end = info.duration.max(0);
+
+    info.track_id = get_string(metadata, "mpris:trackid")
+        .filter(|id| !id.is_empty() && id != NO_TRACK)
+        .unwrap_or_else(|| info.metadata_track_id());
+
+    // MPRIS has no media type; artist/album tags only make sense for audio
+    if !info.artist.is_empty() || !info.album_title.is_empty() {
+        info.media_type = MediaType::Music;
+    }
+
+    info
+}
+
+/// Map an MPRIS `PlaybackStatus` to [`PlaybackState`], ignoring case and surrounding
+/// whitespace. Unknown values are `Stopped`, logged once per value.
+fn playback_state(status: &str) -> PlaybackState {
+    static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    status.trim().to_lowercase().parse().unwrap_or_else(|_| {
+        let mut logged = LOGGED.lock().unwrap();
+        if !logged.iter().any(|s| s == status) {
+            tracing::warn!("Unknown PlaybackStatus {status:?}, assuming stopped");
+            logged.push(status.to_owned());
+        }
+        PlaybackState::Stopped
+    })
+}
+
+/// Map an MPRIS `LoopStatus` to [`RepeatMode`]
+fn repeat_mode(loop_status: &str) -> RepeatMode {
+    match loop_status {
+        "Track" => RepeatMode::Track,
+        "Playlist" => RepeatMode::List,
+        _ => RepeatMode::None,
+    }
+}
+
+fn loop_status(mode: RepeatMode) -> &'static str {
+    match mode {
+        RepeatMode::None => "None",
+        RepeatMode::Track => "Track",
+        RepeatMode::List => "Playlist",
+    }
 }
 
 fn get_i64<StringLike: Into<String>>(meta: &PropMap, key: StringLike) -> Option<i64> {
     refarg_to_i64(meta.get(&key.into())?)
 }
 
+fn get_f64<StringLike: Into<String>>(meta: &PropMap, key: StringLike) -> Option<f64> {
+    meta.get(&key.into())?.as_f64()
+}
+
 fn get_string<StringLike: Into<String>>(meta: &PropMap, key: StringLike) -> Option<String> {
     refarg_to_string(meta.get(&key.into())?)
 }
 
-fn get_first_string<StringLike: Into<String>>(meta: &PropMap, key: StringLike) -> Option<String> {
-    let a = meta.get(&key.into())?;
-    let b = refarg_first(a);
-    refarg_to_string(b)
+/// Strings of a list-typed key, whether the player sent an array or a single string
+fn get_strings(meta: &PropMap, key: &str) -> Vec<String> {
+    let Some(value) = meta.get(key).and_then(|value| value.as_iter()?.next()) else {
+        return Vec::new();
+    };
+
+    if let Some(single) = refarg_to_string(value) {
+        return vec![single];
+    }
+
+    value
+        .as_iter()
+        .map(|items| items.filter_map(refarg_to_string).collect())
+        .unwrap_or_default()
 }
 
 fn refarg_to_string(value: &dyn RefArg) -> Option<String> {
@@ -270,14 +1253,137 @@ fn refarg_to_i64(value: &dyn RefArg) -> Option<i64> {
     value.as_i64()
 }
 
-fn refarg_first(value: &dyn RefArg) -> &dyn RefArg {
-    value
-        .as_iter()
-        .unwrap()
-        .next()
-        .unwrap()
-        .as_iter()
-        .unwrap()
-        .next()
-        .unwrap()
+#[cfg(test)]
+mod tests {
+    use dbus::arg::Variant;
+
+    use super::*;
+
+    fn prop(value: impl RefArg + 'static) -> Variant<Box<dyn RefArg>> {
+        Variant(Box::new(value))
+    }
+
+    #[test]
+    fn test_album_fields() {
+        let metadata = PropMap::from([
+            (String::from("xesam:title"), prop(String::from("Title"))),
+            (
+                String::from("xesam:artist"),
+                prop(vec![String::from("Artist"), String::from("Guest")]),
+            ),
+            (String::from("xesam:album"), prop(String::from("Album"))),
+            (
+                String::from("xesam:genre"),
+                prop(vec![String::from("Rock"), String::from("Pop")]),
+            ),
+            (String::from("xesam:trackNumber"), prop(3_i32)),
+            (String::from("mpris:length"), prop(180_000_000_i64)),
+            (String::from("xesam:userRating"), prop(0.8_f64)),
+            (
+                String::from("xesam:albumArtist"),
+                prop(String::from("Album Artist")),
+            ),
+        ]);
+
+        let info = info_from_metadata(&metadata);
+
+        assert_eq!(info.title, "Title");
+        assert_eq!(info.artist, "Artist, Guest");
+        assert_eq!(info.artists, ["Artist", "Guest"]);
+        assert_eq!(info.album_title, "Album");
+        assert_eq!(info.album_artist, "Album Artist");
+        assert_eq!(info.genres, ["Rock", "Pop"]);
+        assert_eq!(info.track_number, Some(3));
+        assert_eq!((info.seek_start, info.seek_end), (0, 180_000_000));
+        assert_eq!(info.rating, Some(0.8));
+        assert_eq!(info.media_type, MediaType::Music);
+    }
+
+    #[test]
+    fn test_implausible_length_is_live() {
+        let info = |length: i64| {
+            info_from_metadata(&PropMap::from([
+                (String::from("xesam:title"), prop(String::from("Title"))),
+                (String::from("mpris:length"), prop(length)),
+            ]))
+        };
+
+        assert_eq!(info(MAX_PLAUSIBLE_MICROS).duration, MAX_PLAUSIBLE_MICROS);
+        assert!(!info(MAX_PLAUSIBLE_MICROS).is_live);
+
+        // Just past the cap, a 10 minute track in nanoseconds, negative
+        for length in [MAX_PLAUSIBLE_MICROS + 1, 600_000_000_000, -1] {
+            let info = info(length);
+            assert_eq!(info.duration, 0);
+            assert!(info.is_live);
+            assert_eq!(info.seek_end, 0);
+        }
+    }
+
+    #[test]
+    fn test_playback_state() {
+        assert_eq!(playback_state("Playing"), PlaybackState::Playing);
+        assert_eq!(playback_state(" Playing "), PlaybackState::Playing);
+        assert_eq!(playback_state("paused\n"), PlaybackState::Paused);
+        assert_eq!(playback_state("Buffering"), PlaybackState::Stopped);
+    }
+
+    #[test]
+    fn test_closed_stays_empty() {
+        let mut session = MediaSession::default();
+        session.media_info = Some(MediaInfo {
+            title: String::from("Title"),
+            ..Default::default()
+        });
+
+        session.close();
+        session.update();
+
+        assert!(session.get_info().title.is_empty());
+        assert!(session
+            .select_session("org.mpris.MediaPlayer2.any")
+            .is_err());
+    }
+
+    #[test]
+    fn test_artist_shapes() {
+        let scalar = PropMap::from([(String::from("xesam:artist"), prop(String::from("Solo")))]);
+        assert_eq!(info_from_metadata(&scalar).artists, ["Solo"]);
+
+        let empty = PropMap::from([(String::from("xesam:artist"), prop(Vec::<String>::new()))]);
+        assert_eq!(info_from_metadata(&empty).artist, "");
+
+        let wrong = PropMap::from([(String::from("xesam:artist"), prop(7_i32))]);
+        assert!(info_from_metadata(&wrong).artists.is_empty());
+    }
+
+    #[test]
+    fn test_gone_errors() {
+        let error = |name| dbus::Error::new_custom(name, "");
+
+        assert!(is_gone(&error("org.freedesktop.DBus.Error.ServiceUnknown")));
+        assert!(is_gone(&error("org.freedesktop.DBus.Error.NoReply")));
+        assert!(!is_gone(&error(
+            "org.freedesktop.DBus.Error.UnknownProperty"
+        )));
+    }
+
+    #[test]
+    fn test_session_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<MediaSession>();
+    }
+
+    #[test]
+    fn test_with_connection_shares_it() {
+        // Needs a session bus
+        let Ok(connection) = DbusConnection::session() else {
+            return;
+        };
+
+        let session = MediaSession::with_connection(connection.clone()).unwrap();
+        let used = session.connection().unwrap();
+
+        assert!(Arc::ptr_eq(used, &connection.0));
+    }
 }
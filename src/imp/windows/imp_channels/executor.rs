@@ -0,0 +1,49 @@
+use std::future::Future;
+
+use tokio::runtime::{Handle, Runtime};
+
+/// Drives the WinRT futures behind the blocking API: on a runtime of our own, or on
+/// the caller's (see `MediaSessionBuilder::use_existing_runtime`)
+pub enum Executor {
+    Owned(Runtime),
+    Shared(Handle),
+    /// After `shutdown`; nothing may be run anymore
+    Closed,
+}
+
+impl Executor {
+    pub fn new(handle: Option<Handle>) -> std::io::Result<Self> {
+        let Some(handle) = handle else {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            return Ok(Self::Owned(runtime));
+        };
+
+        Ok(Self::Shared(handle))
+    }
+
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match self {
+            Self::Owned(runtime) => runtime.block_on(future),
+            // Blocking one of the runtime's own workers has to be announced
+            Self::Shared(handle) if Handle::try_current().is_ok() => {
+                tokio::task::block_in_place(|| handle.block_on(future))
+            }
+            Self::Shared(handle) => handle.block_on(future),
+            Self::Closed => unreachable!("block_on after shutdown"),
+        }
+    }
+
+    /// Shut our runtime down now, rather than whenever the session is dropped. The
+    /// caller's runtime is left running.
+    pub fn shutdown(&mut self) {
+        if let Self::Owned(runtime) = std::mem::replace(self, Self::Closed) {
+            drop(runtime);
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Closed)
+    }
+}
@@ -1,157 +1,586 @@
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::{
+    future::Future,
+    io::Write,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::{Duration, Instant},
+};
 
 use windows::{
     Foundation::{EventRegistrationToken as WRT_EventToken, TypedEventHandler as WRT_EventHandler},
-    Media::Control::GlobalSystemMediaTransportControlsSessionManager as WRT_MediaManager,
+    Media::Control::{
+        GlobalSystemMediaTransportControlsSession as WRT_MediaSession,
+        GlobalSystemMediaTransportControlsSessionManager as WRT_MediaManager,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus as WRT_PlaybackStatus,
+    },
+};
+
+use crate::{
+    builder::Config,
+    media_event::EventDispatcher,
+    notify::Notify,
+    traits::{MediaSessionApp, MediaSessionBackend, MediaSessionControls},
+    Error, ErrorKind, MediaInfo, PlaybackCapabilities, PlaybackState, PositionInfo, SessionHandle,
 };
 
-use crate::{traits::MediaSessionControls, MediaInfo};
+use super::{executor::Executor, session::Session};
+use crate::imp::windows::utils::playback_state;
 
-use super::session::Session;
+/// How long to hold off picking a session after losing one, so a dying app's session
+/// isn't picked (and lost) again on every update
+const RECONNECT_DEBOUNCE: Duration = Duration::from_millis(500);
 
 enum ManagerEvent {
     CurrentSessionChanged,
+    SessionsChanged,
 }
 
 struct ManagerEventTokens {
     current_session_changed: WRT_EventToken,
+    sessions_changed: WRT_EventToken,
+}
+
+/// When to pick a session again after the current one disconnected
+#[derive(Default)]
+struct Reconnect {
+    at: Option<Instant>,
+}
+
+impl Reconnect {
+    fn lost(&mut self, now: Instant) {
+        self.at = Some(now + RECONNECT_DEBOUNCE);
+    }
+
+    /// Whether to pick a session now: once the debounce is over, or on manager events
+    /// (`changed`) otherwise
+    fn due(&mut self, now: Instant, changed: bool) -> bool {
+        match self.at {
+            Some(at) if now < at => false,
+            Some(_) => {
+                self.at = None;
+                true
+            }
+            None => changed,
+        }
+    }
 }
 
 pub struct MediaSession {
-    runtime: tokio::runtime::Runtime,
+    config: Config,
+    events: EventDispatcher,
+    executor: Executor,
 
     manager: WRT_MediaManager,
     manager_event_channel: (Sender<ManagerEvent>, Receiver<ManagerEvent>),
-    manager_event_tokens: ManagerEventTokens,
+    /// `None` once removed by `close`
+    manager_event_tokens: Option<ManagerEventTokens>,
+    /// Woken by every manager and session event
+    notify: Notify,
 
     session: Option<Session>,
+    reconnect: Reconnect,
+    /// App id set by `select_session`, preferred whenever it has a session
+    pinned: Option<String>,
+
+    #[cfg(feature = "image")]
+    resize_cache: crate::cover::ResizeCache,
 }
 
 impl MediaSession {
-    #[allow(clippy::new_without_default, clippy::missing_panics_doc)]
-    #[must_use]
-    pub fn new() -> Self {
-        let runtime = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
+    /// Fails when the WinRT session manager is unavailable (e.g. Server Core)
+    pub fn new() -> crate::Result<Self> {
+        Self::with_config(Config::default())
+    }
+
+    pub(crate) fn with_config(config: Config) -> crate::Result<Self> {
+        let executor = Executor::new(config.runtime.clone())?;
 
-        let manager = runtime
-            .block_on(WRT_MediaManager::RequestAsync().unwrap())
-            .unwrap();
+        let manager = executor.block_on(WRT_MediaManager::RequestAsync()?)?;
 
+        let notify = Notify::default();
         let manager_event_channel = channel();
         let manager_event_tokens =
-            Self::setup_manager_events(&manager, manager_event_channel.0.clone());
+            Self::setup_manager_events(&manager, manager_event_channel.0.clone(), &notify)?;
 
         let mut self_ = Self {
-            runtime,
+            events: EventDispatcher::with_history(config.track_history),
+            config,
+            executor,
             manager,
             manager_event_channel,
-            manager_event_tokens,
+            manager_event_tokens: Some(manager_event_tokens),
+            notify,
             session: None,
+            reconnect: Reconnect::default(),
+            pinned: None,
+            #[cfg(feature = "image")]
+            resize_cache: crate::cover::ResizeCache::default(),
         };
 
         self_.setup_session();
-        self_
+        Ok(self_)
+    }
+
+    /// Pin the session to the player with `app_id` (`SourceAppUserModelId`, e.g.
+    /// `Spotify.exe`), ignoring priority and the system's choice.
+    ///
+    /// While the player is gone the usual selection applies; it is picked again when
+    /// it returns. Fails if no such player is running now.
+    pub fn select_session(&mut self, app_id: &str) -> crate::Result<()> {
+        if self.executor.is_closed() {
+            return Err(Error::no_active_session("the session is closed"));
+        }
+
+        let found = self
+            .manager
+            .GetSessions()?
+            .into_iter()
+            .any(|s| s.SourceAppUserModelId().is_ok_and(|id| id == app_id));
+
+        if !found {
+            return Err(Error::no_active_session(format!("no session for {app_id}")));
+        }
+
+        self.pinned = Some(app_id.to_owned());
+        self.setup_session();
+
+        Ok(())
+    }
+
+    /// Undo [`select_session`](Self::select_session)
+    pub fn unpin_session(&mut self) {
+        self.pinned = None;
+        self.setup_session();
+    }
+
+    /// Pick the pinned session if present, otherwise by `config` priority, then playing
+    /// state, then whether it's the system's current session, then the order
+    /// `GetSessions` reports
+    fn find_session(&self) -> Option<WRT_MediaSession> {
+        let current = self.manager.GetCurrentSession().ok();
+
+        let Ok(sessions) = self.manager.GetSessions() else {
+            return current;
+        };
+
+        sessions.into_iter().min_by_key(|s| {
+            let app_id = s
+                .SourceAppUserModelId()
+                .map(|id| id.to_string())
+                .unwrap_or_default();
+            let is_playing = s
+                .GetPlaybackInfo()
+                .and_then(|info| info.PlaybackStatus())
+                .is_ok_and(|status| status == WRT_PlaybackStatus::Playing);
+
+            (
+                self.pinned.as_deref() != Some(app_id.as_str()),
+                self.config.priority_rank(&app_id),
+                !is_playing,
+                current.as_ref() != Some(s),
+            )
+        })
     }
 
     fn setup_session(&mut self) {
-        let Ok(wrt_session) = self.manager.GetCurrentSession() else {
+        if self.executor.is_closed() {
+            return;
+        }
+
+        let Some(wrt_session) = self.find_session() else {
+            if self.session.take().is_some() {
+                self.events.session_changed(None);
+            }
             return;
         };
 
-        let mut session = Session::new(wrt_session);
-        self.runtime.block_on(session.update_all());
+        if self.session.as_ref().is_some_and(|s| s.is(&wrt_session)) {
+            return;
+        }
+
+        let session = Session::new(
+            wrt_session,
+            self.config.thumbnail_timeout,
+            self.config.fetch_cover,
+            self.config.position_info(),
+            &self.notify,
+        )
+        .and_then(|mut session| {
+            self.executor.block_on(session.update_all())?;
+            Ok(session)
+        });
 
-        self.session = Some(session);
+        match session {
+            Ok(session) => {
+                let app_id = session.source_app_id();
+                self.session = Some(session);
+                self.events.session_changed(Some(app_id));
+            }
+            Err(e) => self.lose_session(&e),
+        }
+    }
+
+    /// Drop a session whose app went away; the next one is picked after
+    /// [`RECONNECT_DEBOUNCE`]
+    fn lose_session(&mut self, error: &Error) {
+        tracing::warn!("Lost the session: {error}");
+
+        if self.session.take().is_some() {
+            self.events.session_changed(None);
+        }
+        self.reconnect.lost(Instant::now());
     }
 
     fn setup_manager_events(
         manager: &WRT_MediaManager,
         event_sender: Sender<ManagerEvent>,
-    ) -> ManagerEventTokens {
-        let token = manager
-            .CurrentSessionChanged(&WRT_EventHandler::new(move |_, _| {
-                event_sender
-                    .send(ManagerEvent::CurrentSessionChanged)
-                    .unwrap();
+        notify: &Notify,
+    ) -> windows::core::Result<ManagerEventTokens> {
+        let current_session_changed = manager.CurrentSessionChanged(&WRT_EventHandler::new({
+            let sender = event_sender.clone();
+            let notify = notify.clone();
+            move |_, _| {
+                sender.send(ManagerEvent::CurrentSessionChanged).unwrap();
+                notify.wake();
                 Ok(())
-            }))
-            .unwrap();
+            }
+        }))?;
 
-        ManagerEventTokens {
-            current_session_changed: token,
-        }
+        // A new player may outrank the current one
+        let sessions_changed = manager.SessionsChanged(&WRT_EventHandler::new({
+            let notify = notify.clone();
+            move |_, _| {
+                event_sender.send(ManagerEvent::SessionsChanged).unwrap();
+                notify.wake();
+                Ok(())
+            }
+        }))?;
+
+        Ok(ManagerEventTokens {
+            current_session_changed,
+            sessions_changed,
+        })
     }
 
     fn process_manager_events(&mut self) {
-        while let Ok(event) = self.manager_event_channel.1.try_recv() {
+        let mut changed = false;
+        for event in self.manager_event_channel.1.try_iter() {
             match event {
-                ManagerEvent::CurrentSessionChanged => self.setup_session(),
+                ManagerEvent::CurrentSessionChanged | ManagerEvent::SessionsChanged => {
+                    changed = true;
+                }
             }
         }
+
+        if self.reconnect.due(Instant::now(), changed) {
+            self.setup_session();
+        }
     }
 
     pub fn update(&mut self) {
+        if self.executor.is_closed() {
+            return;
+        }
+
         self.process_manager_events();
 
         if let Some(s) = self.session.as_mut() {
-            self.runtime.block_on(s.update());
+            if let Err(e) = self.executor.block_on(s.update()) {
+                self.lose_session(&e);
+            }
+        }
+
+        let info = self.get_info();
+        self.events.dispatch(info, self.config.transition_log_level);
+    }
+
+    pub(crate) fn close_impl(&mut self) {
+        self.remove_manager_events();
+
+        if self.session.take().is_some() {
+            self.events.session_changed(None);
         }
+
+        self.executor.shutdown();
+    }
+
+    fn remove_manager_events(&mut self) {
+        let Some(tokens) = self.manager_event_tokens.take() else {
+            return;
+        };
+
+        if let Err(e) = self
+            .manager
+            .RemoveCurrentSessionChanged(tokens.current_session_changed)
+        {
+            tracing::warn!("Failed to remove CurrentSessionChanged handler: {e}");
+        }
+        if let Err(e) = self.manager.RemoveSessionsChanged(tokens.sessions_changed) {
+            tracing::warn!("Failed to remove SessionsChanged handler: {e}");
+        }
+    }
+
+    pub(crate) fn set_fetch_cover_impl(&mut self, fetch: bool) {
+        self.config.fetch_cover = fetch;
+
+        if let Some(session) = self.session.as_mut() {
+            session.set_fetch_cover(fetch);
+        }
+    }
+
+    pub(crate) fn set_max_extrapolation_impl(&mut self, max: Duration) {
+        self.config.max_extrapolation = max;
+
+        if let Some(session) = self.session.as_mut() {
+            session.set_max_extrapolation(self.config.position_info().max_extrapolation);
+        }
+    }
+
+    /// Re-read the timeline and re-anchor the position extrapolation.
+    ///
+    /// Call on resume from sleep, when the last anchor is far in the past and the
+    /// extrapolated position would otherwise jump ahead until the next timeline event.
+    pub fn resync_position(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.resync_position();
+        }
+    }
+
+    pub(crate) fn notifier(&self) -> Notify {
+        self.notify.clone()
     }
 
+    /// Always: new sessions arrive as events, which only `update` processes
+    #[allow(clippy::unused_self)]
+    pub(crate) fn session_appeared(&self) -> bool {
+        true
+    }
+
+    pub(crate) fn position_info(&self) -> PositionInfo {
+        self.session.as_ref().map_or_else(
+            PositionInfo::default,
+            super::session::Session::position_info,
+        )
+    }
+
+    pub(crate) fn current_info(&self) -> Option<&MediaInfo> {
+        self.session
+            .as_ref()
+            .map(super::session::Session::media_info)
+    }
+
+    pub(crate) fn events(&self) -> &EventDispatcher {
+        &self.events
+    }
+
+    pub(crate) fn events_mut(&mut self) -> &mut EventDispatcher {
+        &mut self.events
+    }
+
+    #[must_use]
     pub fn get_info(&self) -> MediaInfo {
+        let info = self
+            .session
+            .as_ref()
+            .map_or_else(MediaInfo::default, super::session::Session::get_info);
+
+        self.config.finalize(info)
+    }
+
+    /// Every session known to the system, the selected one included.
+    ///
+    /// Other sessions are read on the spot, cover included.
+    #[must_use]
+    pub fn list_sessions(&self) -> Vec<SessionHandle> {
+        if self.executor.is_closed() {
+            return Vec::new();
+        }
+
+        let Ok(sessions) = self.manager.GetSessions() else {
+            return Vec::new();
+        };
+
+        sessions
+            .into_iter()
+            .filter_map(|wrt_session| {
+                let source_app_id = wrt_session.SourceAppUserModelId().ok()?.to_string();
+
+                let info = if self.session.as_ref().is_some_and(|s| s.is(&wrt_session)) {
+                    self.get_info()
+                } else {
+                    let mut session = Session::new(
+                        wrt_session,
+                        self.config.thumbnail_timeout,
+                        self.config.fetch_cover,
+                        self.config.position_info(),
+                        &Notify::default(),
+                    )
+                    .ok()?;
+                    self.executor.block_on(session.update_all()).ok()?;
+                    self.config.finalize(session.get_info())
+                };
+
+                Some(SessionHandle {
+                    source_app_id,
+                    info,
+                })
+            })
+            .collect()
+    }
+
+    /// Playback state of every session known to the system
+    pub(crate) fn session_states(&self) -> Vec<PlaybackState> {
+        let Ok(sessions) = self.manager.GetSessions() else {
+            return Vec::new();
+        };
+
+        sessions
+            .into_iter()
+            .filter_map(|s| {
+                s.GetPlaybackInfo()
+                    .and_then(|info| info.PlaybackStatus())
+                    .ok()
+            })
+            .map(playback_state)
+            .collect()
+    }
+
+    /// Whether there is a player to control; controls are no-ops (`Ok(())`) otherwise
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        self.session.is_some()
+    }
+
+    #[must_use]
+    pub fn get_capabilities(&self) -> PlaybackCapabilities {
         self.session
             .as_ref()
-            .map_or_else(MediaInfo::default, super::session::Session::get_info)
+            .and_then(|s| {
+                s.capabilities()
+                    .inspect_err(|e| tracing::warn!("Failed to read capabilities: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Stream the current cover into `writer` in chunks, without buffering the whole
+    /// image
+    pub fn write_cover_to(&self, mut writer: impl Write) -> crate::Result<()> {
+        let session = self
+            .session
+            .as_ref()
+            .ok_or_else(|| Error::no_active_session("no active session"))?;
+
+        self.executor.block_on(session.write_cover_to(&mut writer))
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn resize_cache(&self) -> &crate::cover::ResizeCache {
+        &self.resize_cache
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn set_control_timeout_impl(&mut self, timeout: Duration) {
+        self.config.control_timeout = timeout;
+    }
+
+    /// Run `control` on the current session, giving up after the control timeout;
+    /// `Ok(())` without a session
+    fn control<'s, F, Fut>(&'s self, control: F) -> crate::Result<()>
+    where
+        F: FnOnce(&'s Session) -> Fut,
+        Fut: Future<Output = crate::Result<()>>,
+    {
+        let Some(session) = &self.session else {
+            return Ok(());
+        };
+
+        self.executor
+            .block_on(async {
+                tokio::time::timeout(self.config.control_timeout, control(session)).await
+            })
+            .map_err(|_| Error::with_kind(ErrorKind::Timeout, "player did not respond in time"))?
     }
 }
 
 impl MediaSessionControls for MediaSession {
     fn next(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.next())?;
-        }
-        Ok(())
+        self.control(|session| session.next())
     }
     fn pause(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.pause())?;
-        }
-        Ok(())
+        self.control(|session| session.pause())
     }
     fn play(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.play())?;
-        }
-        Ok(())
+        self.control(|session| session.play())
     }
     fn prev(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.prev())?;
-        }
-        Ok(())
+        self.control(|session| session.prev())
     }
     fn stop(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.stop())?;
-        }
-        Ok(())
+        self.control(|session| session.stop())
     }
     fn toggle_pause(&self) -> crate::Result<()> {
-        if let Some(session) = &self.session {
-            self.runtime.block_on(session.toggle_pause())?;
-        }
-        Ok(())
+        self.control(|session| session.toggle_pause())
+    }
+    fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        self.control(|session| session.set_position(position_micros))
+    }
+    fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        self.control(|session| session.seek_by(offset_micros))
+    }
+    fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        self.control(|session| session.set_shuffle(on))
+    }
+    fn set_repeat(&self, mode: crate::RepeatMode) -> crate::Result<()> {
+        self.control(|session| session.set_repeat(mode))
+    }
+    fn set_rate(&self, rate: f64) -> crate::Result<()> {
+        self.control(|session| session.set_rate(rate))
+    }
+    fn set_volume(&self, _level: f64) -> crate::Result<()> {
+        // GSMTC has no per-session volume
+        Err(Error::unsupported("volume unsupported on this platform"))
+    }
+    fn supported_controls(&self) -> crate::ControlFlags {
+        self.get_capabilities().into()
+    }
+}
+
+impl MediaSessionApp for MediaSession {
+    fn raise(&self) -> crate::Result<()> {
+        // GSMTC only gives the app id, not a window
+        Err(Error::unsupported(
+            "raising the player unsupported on this platform",
+        ))
+    }
+    fn quit(&self) -> crate::Result<()> {
+        Err(Error::unsupported(
+            "quitting the player unsupported on this platform",
+        ))
+    }
+    fn can_raise(&self) -> bool {
+        false
+    }
+    fn can_quit(&self) -> bool {
+        false
+    }
+}
+
+impl MediaSessionBackend for MediaSession {
+    fn backend_name(&self) -> &'static str {
+        "WinRT (GSMTC)"
+    }
+    fn platform(&self) -> crate::Platform {
+        crate::Platform::Windows
     }
 }
 
 impl Drop for MediaSession {
     fn drop(&mut self) {
-        self.manager
-            .RemoveCurrentSessionChanged(self.manager_event_tokens.current_session_changed)
-            .unwrap();
+        self.remove_manager_events();
     }
 }
 
@@ -159,13 +588,27 @@ impl Drop for MediaSession {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_reconnect_debounce() {
+        let now = Instant::now();
+        let mut reconnect = Reconnect::default();
+
+        assert!(!reconnect.due(now, false));
+        assert!(reconnect.due(now, true));
+
+        reconnect.lost(now);
+        assert!(!reconnect.due(now, true));
+        assert!(reconnect.due(now + RECONNECT_DEBOUNCE, false));
+        assert!(!reconnect.due(now + RECONNECT_DEBOUNCE, false));
+    }
+
     #[test]
     fn test_run() {
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
             .init();
 
-        let mut player = MediaSession::new();
+        let mut player = MediaSession::new().unwrap();
         player.update();
 
         println!("{:#?}", player.get_info());
@@ -1,22 +1,29 @@
 #![allow(clippy::future_not_send)]
 
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::{
+    io::Write,
+    sync::mpsc::{channel, Receiver, Sender},
+    time::Duration,
+};
 
-use base64::{prelude::BASE64_STANDARD, Engine};
 use windows::{
     Foundation::{EventRegistrationToken as WRT_EventToken, TypedEventHandler as WRT_EventHandler},
     Media::Control::{
         GlobalSystemMediaTransportControlsSession as WRT_MediaSession,
         GlobalSystemMediaTransportControlsSessionMediaProperties as WRT_MediaProperties,
         GlobalSystemMediaTransportControlsSessionPlaybackInfo as WRT_PlaybackInfo,
-        GlobalSystemMediaTransportControlsSessionPlaybackStatus as WRT_PlaybackStatus,
         GlobalSystemMediaTransportControlsSessionTimelineProperties as WRT_TimelineProperties,
     },
 };
 
 use crate::{
-    imp::windows::utils::stream_ref_to_bytes, utils::nt_to_unix, MediaInfo, PlaybackState,
-    PositionInfo,
+    imp::windows::utils::{
+        media_type, native_repeat_mode, playback_state, read_thumbnail, repeat_mode, set_cover,
+        stream_ref_write_to,
+    },
+    notify::Notify,
+    utils::nt_to_unix,
+    ErrorKind, MediaInfo, PlaybackCapabilities, PositionInfo,
 };
 
 #[allow(clippy::enum_variant_names)]
@@ -26,6 +33,76 @@ enum SessionEvent {
     TimelinePropertiesChanged,
 }
 
+/// Which parts of the session to re-read for a batch of events
+#[derive(Debug, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools, reason = "plain set of flags")]
+struct Pending {
+    media_properties: bool,
+    playback_info: bool,
+    timeline_properties: bool,
+}
+
+impl Pending {
+    fn collect(events: impl IntoIterator<Item = SessionEvent>) -> Self {
+        let mut pending = Self::default();
+
+        for event in events {
+            match event {
+                SessionEvent::MediaPropertiesChanged => {
+                    pending.media_properties = true;
+                    // A new track has a new duration, whether or not its timeline event
+                    // has arrived yet
+                    pending.timeline_properties = true;
+                }
+                SessionEvent::PlaybackInfoChanged => pending.playback_info = true,
+                SessionEvent::TimelinePropertiesChanged => pending.timeline_properties = true,
+            }
+        }
+
+        pending
+    }
+}
+
+/// The app behind the session exited or stopped answering (`RPC_E_DISCONNECTED` and
+/// the like), so the session is dead
+fn is_disconnected(error: &crate::Error) -> bool {
+    *error.kind() == ErrorKind::Timeout
+}
+
+/// Log a failed read of `what`, passing on only a lost connection
+fn check(result: crate::Result<()>, what: &str) -> crate::Result<()> {
+    match result {
+        Err(e) if is_disconnected(&e) => Err(e),
+        Err(e) => {
+            tracing::warn!("Failed to update {what}: {e}");
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+/// The seekable `(start, end)`: `min..max` from the timeline, or the whole track when
+/// the app leaves them unset (both `0`)
+fn seek_window(min: i64, max: i64, duration: i64) -> (i64, i64) {
+    if max > min {
+        (min, max)
+    } else {
+        (0, duration.max(0))
+    }
+}
+
+/// `position_micros` within the seek window (if the track has one), in the 100ns ticks
+/// Windows expects
+fn seek_ticks(position_micros: i64, seek_start: i64, seek_end: i64) -> i64 {
+    let position = if seek_end > seek_start {
+        position_micros.clamp(seek_start, seek_end)
+    } else {
+        position_micros.max(0)
+    };
+
+    position.saturating_mul(10)
+}
+
 #[allow(clippy::struct_field_names)]
 struct SessionEventTokens {
     media_properties_changed: WRT_EventToken,
@@ -41,136 +118,169 @@ pub struct Session {
 
     media_info: MediaInfo,
     pos_info: PositionInfo,
+
+    thumbnail_timeout: Duration,
+    fetch_cover: bool,
 }
 
 impl Session {
-    pub fn new(wrt_session: WRT_MediaSession) -> Self {
+    /// Fails if the app is already gone
+    pub fn new(
+        wrt_session: WRT_MediaSession,
+        thumbnail_timeout: Duration,
+        fetch_cover: bool,
+        pos_info: PositionInfo,
+        notify: &Notify,
+    ) -> crate::Result<Self> {
         let event_channel = channel();
-        let event_tokens = Self::setup_session_events(&wrt_session, &event_channel.0);
+        let event_tokens = Self::setup_session_events(&wrt_session, &event_channel.0, notify)?;
 
-        Self {
+        Ok(Self {
             inner: wrt_session,
             event_channel,
             event_tokens,
             media_info: MediaInfo::default(),
-            pos_info: PositionInfo::default(),
-        }
+            pos_info,
+            thumbnail_timeout,
+            fetch_cover,
+        })
     }
 
     fn setup_session_events(
         session: &WRT_MediaSession,
         event_sender: &Sender<SessionEvent>,
-    ) -> SessionEventTokens {
-        let media_properties_changed = session
-            .MediaPropertiesChanged(&WRT_EventHandler::new({
-                let sender = event_sender.clone();
-                move |_, _| {
-                    tracing::debug!("Media properties changed");
-                    sender.send(SessionEvent::MediaPropertiesChanged).unwrap();
-                    Ok(())
-                }
-            }))
-            .unwrap();
-
-        let playback_info_changed = session
-            .PlaybackInfoChanged(&WRT_EventHandler::new({
-                let sender = event_sender.clone();
-                move |_, _| {
-                    tracing::debug!("Playback info changed");
-                    sender.send(SessionEvent::PlaybackInfoChanged).unwrap();
-                    Ok(())
-                }
-            }))
-            .unwrap();
+        notify: &Notify,
+    ) -> windows::core::Result<SessionEventTokens> {
+        let media_properties_changed = session.MediaPropertiesChanged(&WRT_EventHandler::new({
+            let sender = event_sender.clone();
+            let notify = notify.clone();
+            move |_, _| {
+                tracing::debug!("Media properties changed");
+                sender.send(SessionEvent::MediaPropertiesChanged).unwrap();
+                notify.wake();
+                Ok(())
+            }
+        }))?;
+
+        let playback_info_changed = session.PlaybackInfoChanged(&WRT_EventHandler::new({
+            let sender = event_sender.clone();
+            let notify = notify.clone();
+            move |_, _| {
+                tracing::debug!("Playback info changed");
+                sender.send(SessionEvent::PlaybackInfoChanged).unwrap();
+                notify.wake();
+                Ok(())
+            }
+        }))?;
 
-        let timeline_properties_changed = session
-            .TimelinePropertiesChanged(&WRT_EventHandler::new({
+        let timeline_properties_changed =
+            session.TimelinePropertiesChanged(&WRT_EventHandler::new({
                 let sender = event_sender.clone();
+                let notify = notify.clone();
                 move |_, _| {
                     tracing::debug!("Timeline properties changed");
                     sender
                         .send(SessionEvent::TimelinePropertiesChanged)
                         .unwrap();
+                    notify.wake();
                     Ok(())
                 }
-            }))
-            .unwrap();
+            }))?;
 
-        SessionEventTokens {
+        Ok(SessionEventTokens {
             media_properties_changed,
             playback_info_changed,
             timeline_properties_changed,
-        }
+        })
     }
 
+    /// Fails on a disconnected session, so errors are only logged
     fn drop_session_events(session: &WRT_MediaSession, tokens: &SessionEventTokens) {
-        session
+        let removed = session
             .RemoveMediaPropertiesChanged(tokens.media_properties_changed)
-            .unwrap();
-        session
-            .RemovePlaybackInfoChanged(tokens.playback_info_changed)
-            .unwrap();
-        session
-            .RemoveTimelinePropertiesChanged(tokens.timeline_properties_changed)
-            .unwrap();
+            .and_then(|()| session.RemovePlaybackInfoChanged(tokens.playback_info_changed))
+            .and_then(|()| {
+                session.RemoveTimelinePropertiesChanged(tokens.timeline_properties_changed)
+            });
+
+        if let Err(e) = removed {
+            tracing::debug!("Failed to remove session events: {e}");
+        }
     }
 
-    async fn process_events(&mut self) {
-        while let Ok(event) = self.event_channel.1.try_recv() {
-            _ = match event {
-                SessionEvent::MediaPropertiesChanged => self
-                    .update_media_properties()
-                    .await
-                    .inspect_err(|e| tracing::warn!("Failed to update media properties: {e}")),
-                SessionEvent::PlaybackInfoChanged => self.update_playback_info(),
-                SessionEvent::TimelinePropertiesChanged => self.update_timeline_properties(),
-            }
+    /// Apply everything that happened since the last call in one go.
+    ///
+    /// Events arrive from WinRT threads one part at a time; re-reading every touched part
+    /// together (and the timeline with every new track) keeps `get_info` from mixing
+    /// the new track's metadata with the old one's duration.
+    async fn process_events(&mut self) -> crate::Result<()> {
+        let pending = Pending::collect(self.event_channel.1.try_iter());
+
+        if pending.media_properties {
+            check(self.update_media_properties().await, "media properties")?;
+        }
+        if pending.playback_info {
+            check(self.update_playback_info(), "playback info")?;
         }
+        if pending.timeline_properties {
+            check(self.update_timeline_properties(), "timeline properties")?;
+        }
+
+        Ok(())
     }
 
-    async fn update_media_properties(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn update_media_properties(&mut self) -> crate::Result<()> {
         tracing::debug!("Update: media properties");
 
         let props: WRT_MediaProperties = self.inner.TryGetMediaPropertiesAsync()?.await?;
 
         self.media_info.title = props.Title()?.to_string();
         self.media_info.artist = props.Artist()?.to_string();
+        // GSMTC has a single artist string
+        self.media_info.artists = [&self.media_info.artist]
+            .into_iter()
+            .filter(|a| !a.is_empty())
+            .cloned()
+            .collect();
         self.media_info.album_title = props.AlbumTitle()?.to_string();
         self.media_info.album_artist = props.AlbumArtist()?.to_string();
-
-        match props.Thumbnail() {
-            Ok(ref_) => {
-                let thumb = stream_ref_to_bytes(ref_).await?;
-                self.media_info.cover_raw.clone_from(&thumb);
-
-                let b64 = BASE64_STANDARD.encode(thumb);
-                self.media_info.cover_b64 = b64;
-            }
-            Err(_) => {
-                tracing::error!("Failed to get thumbnail");
-            }
-        }
+        self.media_info.track_id = self.media_info.metadata_track_id();
+        self.media_info.genres = props.Genres()?.into_iter().map(|g| g.to_string()).collect();
+        // 0 when the app doesn't report them
+        self.media_info.track_number = u32::try_from(props.TrackNumber()?).ok().filter(|&n| n > 0);
+        self.media_info.album_track_count = u32::try_from(props.AlbumTrackCount()?)
+            .ok()
+            .filter(|&n| n > 0);
+        self.media_info.media_type = media_type(props.PlaybackType());
+
+        let thumb = if self.fetch_cover {
+            read_thumbnail(props.Thumbnail(), self.thumbnail_timeout).await
+        } else {
+            None
+        };
+        set_cover(&mut self.media_info, thumb);
 
         Ok(())
     }
 
-    fn update_playback_info(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn update_playback_info(&mut self) -> crate::Result<()> {
         tracing::debug!("Update: playback info");
 
         let props: WRT_PlaybackInfo = self.inner.GetPlaybackInfo()?;
 
-        self.media_info.state = match props.PlaybackStatus()? {
-            WRT_PlaybackStatus::Playing => PlaybackState::Playing.into(),
-            WRT_PlaybackStatus::Paused => PlaybackState::Paused.into(),
-            _ => PlaybackState::Stopped.into(),
-        };
+        let state = playback_state(props.PlaybackStatus()?);
+        self.media_info.set_state(state, &mut self.pos_info);
 
+        self.media_info.source_app_id = self.inner.SourceAppUserModelId()?.to_string();
+        self.media_info.media_type = media_type(props.PlaybackType());
+        self.media_info.shuffle = props.IsShuffleActive().and_then(|s| s.Value()).ok();
+        self.media_info.repeat = repeat_mode(props.AutoRepeatMode());
         self.pos_info.playback_rate = props.PlaybackRate()?.Value()?;
 
         Ok(())
     }
 
-    fn update_timeline_properties(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn update_timeline_properties(&mut self) -> crate::Result<()> {
         tracing::debug!("Update: timeline properties");
 
         let props: WRT_TimelineProperties = self.inner.GetTimelineProperties()?;
@@ -178,6 +288,11 @@ impl Session {
         // Windows' value is in seconds * 10^-7 (100 nanoseconds)
         // Mapping to micros (10^-6)
         self.media_info.duration = props.EndTime()?.Duration / 10;
+        (self.media_info.seek_start, self.media_info.seek_end) = seek_window(
+            props.MinSeekTime()?.Duration / 10,
+            props.MaxSeekTime()?.Duration / 10,
+            self.media_info.duration,
+        );
         self.pos_info.pos_raw = props.Position()?.Duration / 10;
 
         // NT to UNIX in micros
@@ -186,20 +301,86 @@ impl Session {
         Ok(())
     }
 
-    pub async fn update_all(&mut self) {
-        _ = self.update_media_properties().await;
-        _ = self.update_playback_info();
-        _ = self.update_timeline_properties();
+    /// Fails only if the session is disconnected
+    pub async fn update_all(&mut self) -> crate::Result<()> {
+        check(self.update_media_properties().await, "media properties")?;
+        check(self.update_playback_info(), "playback info")?;
+        check(self.update_timeline_properties(), "timeline properties")
     }
 
-    pub async fn update(&mut self) {
-        self.process_events().await;
+    /// Fails only if the session is disconnected
+    pub async fn update(&mut self) -> crate::Result<()> {
+        self.process_events().await
+    }
+
+    /// Age in microseconds of the last timeline report to stop extrapolating at
+    pub fn set_max_extrapolation(&mut self, max_extrapolation: i64) {
+        self.pos_info.max_extrapolation = max_extrapolation;
+    }
+
+    /// Re-reads the media properties on the next `update`, to drop or fetch the cover
+    pub fn set_fetch_cover(&mut self, fetch: bool) {
+        self.fetch_cover = fetch;
+        _ = self
+            .event_channel
+            .0
+            .send(SessionEvent::MediaPropertiesChanged);
+    }
+
+    pub fn resync_position(&mut self) {
+        _ = self
+            .update_timeline_properties()
+            .inspect_err(|e| tracing::warn!("Failed to resync position: {e}"));
+    }
+
+    pub fn source_app_id(&self) -> String {
+        self.inner
+            .SourceAppUserModelId()
+            .map(|id| id.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Whether this wraps `wrt_session`
+    pub fn is(&self, wrt_session: &WRT_MediaSession) -> bool {
+        self.inner == *wrt_session
     }
 
     pub fn get_info(&self) -> MediaInfo {
         self.media_info.with_position(&self.pos_info)
     }
 
+    pub fn media_info(&self) -> &MediaInfo {
+        &self.media_info
+    }
+
+    pub fn position_info(&self) -> PositionInfo {
+        self.pos_info.clone()
+    }
+
+    pub fn capabilities(&self) -> crate::Result<PlaybackCapabilities> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+
+        Ok(PlaybackCapabilities {
+            can_play: controls.IsPlayEnabled()?,
+            can_pause: controls.IsPauseEnabled()?,
+            can_stop: controls.IsStopEnabled()?,
+            can_next: controls.IsNextEnabled()?,
+            can_prev: controls.IsPreviousEnabled()?,
+            can_seek: controls.IsPlaybackPositionEnabled()?,
+            can_shuffle: controls.IsShuffleEnabled()?,
+            can_repeat: controls.IsRepeatEnabled()?,
+            can_set_rate: controls.IsPlaybackRateEnabled()?,
+            // GSMTC has no per-session volume
+            can_set_volume: false,
+        })
+    }
+
+    pub async fn write_cover_to(&self, writer: &mut impl Write) -> crate::Result<()> {
+        let props: WRT_MediaProperties = self.inner.TryGetMediaPropertiesAsync()?.await?;
+
+        stream_ref_write_to(props.Thumbnail()?, writer).await
+    }
+
     //
     // Controls
     //
@@ -233,6 +414,73 @@ impl Session {
         self.inner.TrySkipPreviousAsync()?.await?;
         Ok(())
     }
+
+    pub async fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        let ticks = seek_ticks(
+            position_micros,
+            self.media_info.seek_start,
+            self.media_info.seek_end,
+        );
+
+        if self.inner.TryChangePlaybackPositionAsync(ticks)?.await? {
+            Ok(())
+        } else {
+            Err(crate::Error::unsupported("player does not support seeking"))
+        }
+    }
+
+    pub async fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        let info = self.get_info();
+        let position = (info.position + offset_micros).clamp(info.seek_start, info.seek_end);
+
+        self.set_position(position).await
+    }
+
+    pub async fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsShuffleEnabled()? {
+            return Err(crate::Error::unsupported("player does not support shuffle"));
+        }
+
+        if self.inner.TryChangeShuffleActiveAsync(on)?.await? {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change shuffle"))
+        }
+    }
+
+    pub async fn set_repeat(&self, mode: crate::RepeatMode) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsRepeatEnabled()? {
+            return Err(crate::Error::unsupported("player does not support repeat"));
+        }
+
+        if self
+            .inner
+            .TryChangeAutoRepeatModeAsync(native_repeat_mode(mode))?
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change repeat mode"))
+        }
+    }
+
+    pub async fn set_rate(&self, rate: f64) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsPlaybackRateEnabled()? {
+            return Err(crate::Error::unsupported(
+                "player does not support changing the rate",
+            ));
+        }
+
+        // No range is advertised; the player refuses what it can't do
+        if self.inner.TryChangePlaybackRateAsync(rate)?.await? {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change the rate"))
+        }
+    }
 }
 
 impl Drop for Session {
@@ -240,3 +488,80 @@ impl Drop for Session {
         Self::drop_session_events(&self.inner, &self.event_tokens);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rapid_track_changes_collapse() {
+        let (tx, rx) = channel();
+
+        for i in 0..10_000 {
+            tx.send(if i % 2 == 0 {
+                SessionEvent::MediaPropertiesChanged
+            } else {
+                SessionEvent::PlaybackInfoChanged
+            })
+            .unwrap();
+        }
+
+        let pending = Pending::collect(rx.try_iter());
+
+        assert_eq!(
+            pending,
+            Pending {
+                media_properties: true,
+                playback_info: true,
+                timeline_properties: true,
+            }
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_seek_window() {
+        assert_eq!(seek_window(60, 600, 900), (60, 600));
+        assert_eq!(seek_window(0, 0, 900), (0, 900));
+        assert_eq!(seek_window(0, 0, -1), (0, 0));
+    }
+
+    #[test]
+    fn test_seek_ticks() {
+        assert_eq!(seek_ticks(30, 0, 900), 300);
+        assert_eq!(seek_ticks(1_000, 60, 600), 6_000);
+        assert_eq!(seek_ticks(-5, 60, 600), 600);
+        // No known window: only kept non-negative, and never overflows
+        assert_eq!(seek_ticks(-5, 0, 0), 0);
+        assert_eq!(seek_ticks(i64::MAX, 0, 0), i64::MAX);
+    }
+
+    #[test]
+    fn test_playback_only() {
+        let pending = Pending::collect([SessionEvent::PlaybackInfoChanged]);
+
+        assert_eq!(
+            pending,
+            Pending {
+                playback_info: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_only_disconnect_is_passed_on() {
+        // RPC_E_DISCONNECTED, as returned once the app behind the session exits, and
+        // E_FAIL
+        #[allow(clippy::cast_possible_wrap)]
+        let disconnected = windows::core::HRESULT(0x8001_0108_u32 as i32);
+        #[allow(clippy::cast_possible_wrap)]
+        let other = windows::core::HRESULT(0x8000_4005_u32 as i32);
+
+        let error = |code| Err(windows::core::Error::from(code).into());
+
+        assert!(check(error(disconnected), "test").is_err());
+        assert!(check(error(other), "test").is_ok());
+        assert!(check(Ok(()), "test").is_ok());
+    }
+}
@@ -159,6 +159,18 @@ impl MediaSession {
         None
     }
 
+    /// Re-read the timeline and re-anchor the position extrapolation, e.g. after resume
+    /// from sleep
+    pub fn resync_position(&self) {
+        self.rt.block_on(async {
+            if let Some(session) = &mut *self.session.lock().await {
+                _ = session
+                    .update_timeline_properties()
+                    .inspect_err(|e| tracing::warn!("Failed to resync position: {e}"));
+            }
+        });
+    }
+
     #[must_use]
     pub fn get_info(&self) -> MediaInfo {
         let session = self.rt.block_on(self.session.lock());
@@ -218,4 +230,56 @@ impl MediaSessionControls for MediaSession {
         }
         Ok(())
     }
+
+    fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        let opt = self.rt.block_on(self.session.lock());
+        if let Some(session) = &*opt {
+            self.rt.block_on(session.set_position(position_micros))?;
+        }
+        Ok(())
+    }
+
+    fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        let opt = self.rt.block_on(self.session.lock());
+        if let Some(session) = &*opt {
+            self.rt.block_on(session.seek_by(offset_micros))?;
+        }
+        Ok(())
+    }
+
+    fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        let opt = self.rt.block_on(self.session.lock());
+        if let Some(session) = &*opt {
+            self.rt.block_on(session.set_shuffle(on))?;
+        }
+        Ok(())
+    }
+
+    fn set_repeat(&self, mode: crate::RepeatMode) -> crate::Result<()> {
+        let opt = self.rt.block_on(self.session.lock());
+        if let Some(session) = &*opt {
+            self.rt.block_on(session.set_repeat(mode))?;
+        }
+        Ok(())
+    }
+
+    fn set_rate(&self, rate: f64) -> crate::Result<()> {
+        let opt = self.rt.block_on(self.session.lock());
+        if let Some(session) = &*opt {
+            self.rt.block_on(session.set_rate(rate))?;
+        }
+        Ok(())
+    }
+
+    fn supported_controls(&self) -> crate::ControlFlags {
+        // Capabilities are not read by this implementation
+        crate::ControlFlags::empty()
+    }
+
+    fn set_volume(&self, _level: f64) -> crate::Result<()> {
+        // GSMTC has no per-session volume
+        Err(crate::Error::unsupported(
+            "volume unsupported on this platform",
+        ))
+    }
 }
@@ -1,6 +1,5 @@
-use std::{cmp::min, fmt::Debug};
+use std::{fmt::Debug, time::Duration};
 
-use base64::{display::Base64Display, engine::general_purpose::STANDARD};
 use windows::{
     Foundation::EventRegistrationToken,
     Media::Control::{
@@ -12,8 +11,11 @@ use windows::{
     },
 };
 
-use crate::imp::windows::utils::stream_ref_to_bytes;
-use crate::utils::{micros_since_epoch, nt_to_unix};
+use crate::builder::DEFAULT_THUMBNAIL_TIMEOUT;
+use crate::imp::windows::utils::{
+    media_type, native_repeat_mode, read_thumbnail, repeat_mode, set_cover,
+};
+use crate::utils::nt_to_unix;
 use crate::{MediaInfo, PlaybackState, PositionInfo};
 
 #[derive(Clone, Debug)]
@@ -29,6 +31,8 @@ pub(super) struct Session {
 
     media_info: MediaInfo,
     pos_info: PositionInfo,
+
+    thumbnail_timeout: Duration,
 }
 
 impl Session {
@@ -41,6 +45,7 @@ impl Session {
             pos_info,
             inner: wrt_session,
             event_tokens: None,
+            thumbnail_timeout: DEFAULT_THUMBNAIL_TIMEOUT,
         }
     }
 
@@ -90,12 +95,20 @@ impl Session {
 
         let props: PlaybackInfo = self.inner.GetPlaybackInfo()?;
 
-        self.media_info.state = match props.PlaybackStatus()? {
-            PlaybackStatus::Playing => PlaybackState::Playing.into(),
-            PlaybackStatus::Paused => PlaybackState::Paused.into(),
-            _ => PlaybackState::Stopped.into(),
+        let state = match props.PlaybackStatus()? {
+            PlaybackStatus::Playing => PlaybackState::Playing,
+            PlaybackStatus::Paused => PlaybackState::Paused,
+            PlaybackStatus::Changing => PlaybackState::Changing,
+            PlaybackStatus::Opened => PlaybackState::Opening,
+            PlaybackStatus::Closed => PlaybackState::Closed,
+            _ => PlaybackState::Stopped,
         };
+        self.media_info.set_state(state, &mut self.pos_info);
 
+        self.media_info.source_app_id = self.inner.SourceAppUserModelId()?.to_string();
+        self.media_info.media_type = media_type(props.PlaybackType());
+        self.media_info.shuffle = props.IsShuffleActive().and_then(|s| s.Value()).ok();
+        self.media_info.repeat = repeat_mode(props.AutoRepeatMode());
         self.pos_info.playback_rate = props.PlaybackRate()?.Value()?;
 
         Ok(())
@@ -109,21 +122,25 @@ impl Session {
 
         self.media_info.title = props.Title()?.to_string();
         self.media_info.artist = props.Artist()?.to_string();
+        // GSMTC has a single artist string
+        self.media_info.artists = [&self.media_info.artist]
+            .into_iter()
+            .filter(|a| !a.is_empty())
+            .cloned()
+            .collect();
         self.media_info.album_title = props.AlbumTitle()?.to_string();
         self.media_info.album_artist = props.AlbumArtist()?.to_string();
-
-        match props.Thumbnail() {
-            Ok(ref_) => {
-                let thumb = stream_ref_to_bytes(ref_).await?;
-                self.media_info.cover_raw.clone_from(&thumb);
-
-                let b64 = Base64Display::new(&thumb, &STANDARD).to_string();
-                self.media_info.cover_b64 = b64;
-            }
-            Err(_) => {
-                tracing::error!("Failed to get thumbnail");
-            }
-        }
+        self.media_info.track_id = self.media_info.metadata_track_id();
+        self.media_info.genres = props.Genres()?.into_iter().map(|g| g.to_string()).collect();
+        // 0 when the app doesn't report them
+        self.media_info.track_number = u32::try_from(props.TrackNumber()?).ok().filter(|&n| n > 0);
+        self.media_info.album_track_count = u32::try_from(props.AlbumTrackCount()?)
+            .ok()
+            .filter(|&n| n > 0);
+        self.media_info.media_type = media_type(props.PlaybackType());
+
+        let thumb = read_thumbnail(props.Thumbnail(), self.thumbnail_timeout).await;
+        set_cover(&mut self.media_info, thumb);
 
         Ok(())
     }
@@ -181,6 +198,72 @@ impl Session {
         self.inner.TrySkipPreviousAsync()?.await?;
         Ok(())
     }
+
+    pub async fn set_position(&self, position_micros: i64) -> crate::Result<()> {
+        // Windows expects 100ns ticks
+        if self
+            .inner
+            .TryChangePlaybackPositionAsync(position_micros * 10)?
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(crate::Error::unsupported("player does not support seeking"))
+        }
+    }
+
+    pub async fn seek_by(&self, offset_micros: i64) -> crate::Result<()> {
+        let info = self.get_info();
+        let position = (info.position + offset_micros).clamp(0, info.duration.max(0));
+
+        self.set_position(position).await
+    }
+
+    pub async fn set_shuffle(&self, on: bool) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsShuffleEnabled()? {
+            return Err(crate::Error::unsupported("player does not support shuffle"));
+        }
+
+        if self.inner.TryChangeShuffleActiveAsync(on)?.await? {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change shuffle"))
+        }
+    }
+
+    pub async fn set_repeat(&self, mode: crate::RepeatMode) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsRepeatEnabled()? {
+            return Err(crate::Error::unsupported("player does not support repeat"));
+        }
+
+        if self
+            .inner
+            .TryChangeAutoRepeatModeAsync(native_repeat_mode(mode))?
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change repeat mode"))
+        }
+    }
+
+    pub async fn set_rate(&self, rate: f64) -> crate::Result<()> {
+        let controls = self.inner.GetPlaybackInfo()?.Controls()?;
+        if !controls.IsPlaybackRateEnabled()? {
+            return Err(crate::Error::unsupported(
+                "player does not support changing the rate",
+            ));
+        }
+
+        // No range is advertised; the player refuses what it can't do
+        if self.inner.TryChangePlaybackRateAsync(rate)?.await? {
+            Ok(())
+        } else {
+            Err(crate::Error::new("player refused to change the rate"))
+        }
+    }
 }
 
 impl Drop for Session {
@@ -1,3 +1,9 @@
+//! Windows backend over GSMTC (`Windows.Media.Control`).
+//!
+//! `imp_channels` is the backend in use and the supported one. `imp_mutex` is an
+//! earlier design that shares state with the event handlers behind a mutex; it is
+//! kept for reference only and not exported.
+
 mod utils;
 
 mod imp_channels;
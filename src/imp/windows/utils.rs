@@ -1,26 +1,227 @@
-use windows::Storage::Streams::{
-    Buffer as WRT_Buffer, DataReader as WRT_DataReader,
-    IRandomAccessStreamReference as WRT_IStreamRef,
-    IRandomAccessStreamWithContentType as WRT_IStream, InputStreamOptions,
+use std::{io::Write, time::Duration};
+
+use windows::{
+    Foundation::IReference as WRT_IReference,
+    Media::{
+        Control::GlobalSystemMediaTransportControlsSessionPlaybackStatus as WRT_PlaybackStatus,
+        MediaPlaybackAutoRepeatMode as WRT_AutoRepeatMode,
+        MediaPlaybackType as WRT_MediaPlaybackType,
+    },
+    Storage::Streams::{
+        Buffer as WRT_Buffer, DataReader as WRT_DataReader,
+        IRandomAccessStreamReference as WRT_IStreamRef,
+        IRandomAccessStreamWithContentType as WRT_IStream, InputStreamOptions,
+    },
 };
 
+use base64::{prelude::BASE64_STANDARD, Engine};
+
+use crate::{
+    utils::{fnv1a_64, image_mime, UNKNOWN_MIME},
+    MediaInfo, MediaType, PlaybackState, RepeatMode,
+};
+
+/// Map an optional `PlaybackType` (not every app reports one) to [`MediaType`]
+pub fn media_type(
+    playback_type: windows::core::Result<WRT_IReference<WRT_MediaPlaybackType>>,
+) -> MediaType {
+    match playback_type.and_then(|t| t.Value()) {
+        Ok(WRT_MediaPlaybackType::Music) => MediaType::Music,
+        Ok(WRT_MediaPlaybackType::Video) => MediaType::Video,
+        Ok(WRT_MediaPlaybackType::Image) => MediaType::Image,
+        _ => MediaType::Unknown,
+    }
+}
+
+pub fn playback_state(status: WRT_PlaybackStatus) -> PlaybackState {
+    match status {
+        WRT_PlaybackStatus::Playing => PlaybackState::Playing,
+        WRT_PlaybackStatus::Paused => PlaybackState::Paused,
+        WRT_PlaybackStatus::Changing => PlaybackState::Changing,
+        WRT_PlaybackStatus::Opened => PlaybackState::Opening,
+        WRT_PlaybackStatus::Closed => PlaybackState::Closed,
+        _ => PlaybackState::Stopped,
+    }
+}
+
+/// Map an optional `AutoRepeatMode` to [`RepeatMode`]
+pub fn repeat_mode(
+    repeat_mode: windows::core::Result<WRT_IReference<WRT_AutoRepeatMode>>,
+) -> RepeatMode {
+    match repeat_mode.and_then(|m| m.Value()) {
+        Ok(WRT_AutoRepeatMode::Track) => RepeatMode::Track,
+        Ok(WRT_AutoRepeatMode::List) => RepeatMode::List,
+        _ => RepeatMode::None,
+    }
+}
+
+pub fn native_repeat_mode(mode: RepeatMode) -> WRT_AutoRepeatMode {
+    match mode {
+        RepeatMode::None => WRT_AutoRepeatMode::None,
+        RepeatMode::Track => WRT_AutoRepeatMode::Track,
+        RepeatMode::List => WRT_AutoRepeatMode::List,
+    }
+}
+
+/// Read the whole stream, in the same bounded chunks as [`stream_ref_write_to`]
 #[allow(clippy::future_not_send)]
 pub async fn stream_ref_to_bytes(stream_ref: WRT_IStreamRef) -> crate::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    stream_ref_write_to(stream_ref, &mut bytes).await?;
+
+    Ok(bytes)
+}
+
+/// Copy the stream into `writer` in bounded chunks, without buffering all of it
+#[allow(clippy::future_not_send)]
+pub async fn stream_ref_write_to(
+    stream_ref: WRT_IStreamRef,
+    writer: &mut impl Write,
+) -> crate::Result<()> {
+    const CHUNK_SIZE: u32 = 64 * 1024;
+
     let readable_stream: WRT_IStream = stream_ref.OpenReadAsync()?.await?;
-    #[allow(clippy::cast_possible_truncation)]
-    let read_size = readable_stream.Size()? as u32;
-    let buffer: WRT_Buffer = WRT_Buffer::Create(read_size)?;
+    let buffer: WRT_Buffer = WRT_Buffer::Create(CHUNK_SIZE)?;
+    let mut chunk: Vec<u8> = Vec::with_capacity(CHUNK_SIZE as usize);
+
+    loop {
+        let ib = readable_stream
+            .ReadAsync(&buffer, CHUNK_SIZE, InputStreamOptions::None)?
+            .await?;
+
+        let len = ib.Length()? as usize;
+        if len == 0 {
+            break;
+        }
+
+        chunk.resize(len, 0);
+        WRT_DataReader::FromBuffer(&ib)?.ReadBytes(&mut chunk)?;
+        writer.write_all(&chunk)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// [`stream_ref_to_bytes`], giving up after `timeout` (`Ok(None)`)
+#[allow(clippy::future_not_send)]
+pub async fn stream_ref_to_bytes_timeout(
+    stream_ref: WRT_IStreamRef,
+    timeout: Duration,
+) -> crate::Result<Option<Vec<u8>>> {
+    if let Ok(bytes) = tokio::time::timeout(timeout, stream_ref_to_bytes(stream_ref)).await {
+        bytes.map(Some)
+    } else {
+        tracing::warn!("Thumbnail read timed out after {timeout:?}");
+        Ok(None)
+    }
+}
+
+/// Read a session thumbnail; `None` if it is missing, unreadable, empty or too slow
+#[allow(clippy::future_not_send)]
+pub async fn read_thumbnail(
+    thumbnail: windows::core::Result<WRT_IStreamRef>,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    let stream_ref = thumbnail
+        .inspect_err(|e| tracing::error!("Failed to get thumbnail: {e}"))
+        .ok()?;
+
+    match stream_ref_to_bytes_timeout(stream_ref, timeout).await {
+        Ok(thumb) => thumb.filter(|bytes| !bytes.is_empty()),
+        Err(e) => {
+            tracing::error!("Failed to read thumbnail: {e}");
+            None
+        }
+    }
+}
+
+/// Replace the cover fields, clearing them so the previous track's art doesn't linger.
+///
+/// Apps resend the same thumbnail with every metadata change; that skips the base64
+/// encoding.
+pub fn set_cover(info: &mut MediaInfo, thumb: Option<Vec<u8>>) {
+    if let Some(thumb) = thumb {
+        let hash = fnv1a_64(&thumb);
+        if hash == info.cover_hash && !info.cover_raw.is_empty() {
+            tracing::debug!("Cover unchanged");
+            return;
+        }
+
+        info.cover_hash = hash;
+        info.cover_mime = image_mime(&thumb).to_owned();
+        info.cover_b64 = BASE64_STANDARD.encode(&thumb);
+        info.cover_raw = thumb;
+    } else {
+        info.cover_hash = 0;
+        info.cover_mime = UNKNOWN_MIME.to_owned();
+        info.cover_raw.clear();
+        info.cover_b64.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::{
+        core::HSTRING,
+        Foundation::Uri,
+        Storage::Streams::{InMemoryRandomAccessStream, RandomAccessStreamReference},
+    };
+
+    use super::*;
+
+    fn with_cover() -> MediaInfo {
+        let mut info = MediaInfo::default();
+        set_cover(&mut info, Some(b"\x89PNG\r\n\x1a\n".to_vec()));
+        assert!(!info.cover_raw.is_empty());
+        info
+    }
+
+    fn read(thumbnail: windows::core::Result<WRT_IStreamRef>) -> Option<Vec<u8>> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(read_thumbnail(thumbnail, Duration::from_secs(1)))
+    }
+
+    fn assert_cleared(info: &MediaInfo) {
+        assert!(info.cover_raw.is_empty());
+        assert!(info.cover_b64.is_empty());
+        assert_eq!(info.cover_hash, 0);
+        assert_eq!(info.cover_mime, UNKNOWN_MIME);
+    }
+
+    #[test]
+    fn test_same_thumbnail_is_not_reencoded() {
+        let mut info = with_cover();
+        info.cover_b64 = String::from("cached");
+
+        let same = info.cover_raw.clone();
+        set_cover(&mut info, Some(same));
+        assert_eq!(info.cover_b64, "cached");
+    }
 
-    let ib = readable_stream
-        .ReadAsync(&buffer, read_size, InputStreamOptions::ReadAhead)?
-        .await?;
+    #[test]
+    fn test_failing_thumbnail_clears_cover() {
+        let uri = Uri::CreateUri(&HSTRING::from("file:///nonexistent/cover.png")).unwrap();
+        let stream_ref = RandomAccessStreamReference::CreateFromUri(&uri)
+            .and_then(|r| windows::core::Interface::cast(&r));
 
-    let reader: WRT_DataReader = WRT_DataReader::FromBuffer(&ib)?;
-    let len = ib.Length()? as usize;
-    let mut rv: Vec<u8> = vec![0; len];
-    let res: &mut [u8] = rv.as_mut_slice();
+        let mut info = with_cover();
+        set_cover(&mut info, read(stream_ref));
+        assert_cleared(&info);
+    }
 
-    reader.ReadBytes(res)?;
+    #[test]
+    fn test_empty_thumbnail_clears_cover() {
+        let stream = InMemoryRandomAccessStream::new().unwrap();
+        let stream_ref = RandomAccessStreamReference::CreateFromStream(&stream)
+            .and_then(|r| windows::core::Interface::cast(&r));
 
-    Ok(rv)
+        let mut info = with_cover();
+        set_cover(&mut info, read(stream_ref));
+        assert_cleared(&info);
+    }
 }
@@ -0,0 +1,101 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{media_event::EventDispatcher, MediaEvent, MediaInfo, MediaSession};
+
+/// [`MediaInfo`] updates as a [`Stream`], see [`MediaSession::info_stream`]
+pub struct InfoStream<'a> {
+    session: &'a mut MediaSession,
+    notify: crate::notify::Notify,
+    /// Detects seeks, which only show in the position
+    seen: EventDispatcher,
+    /// Last yielded, to skip updates that changed nothing
+    last: Option<MediaInfo>,
+}
+
+impl<'a> InfoStream<'a> {
+    pub(crate) fn new(session: &'a mut MediaSession) -> Self {
+        let notify = session.notifier();
+
+        Self {
+            session,
+            notify,
+            seen: EventDispatcher::default(),
+            last: None,
+        }
+    }
+}
+
+/// Whether `info` differs from `last` in more than the extrapolated position
+fn changed_since(last: &MediaInfo, info: &mut MediaInfo) -> bool {
+    // Swapped in and back to compare without cloning the cover
+    let position = std::mem::replace(&mut info.position, last.position);
+    let last_updated = std::mem::replace(&mut info.last_updated, last.last_updated);
+
+    let changed = *last != *info;
+
+    info.position = position;
+    info.last_updated = last_updated;
+    changed
+}
+
+impl Stream for InfoStream<'_> {
+    type Item = MediaInfo;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<MediaInfo>> {
+        let this = &mut *self;
+
+        // Register before updating, so an event landing in between isn't lost
+        this.notify.register(cx.waker());
+        this.session.update();
+
+        let mut info = this.session.get_info();
+        let seeked = this
+            .seen
+            .dispatch(info.clone(), None)
+            .contains(&MediaEvent::Seeked);
+
+        let changed = match &this.last {
+            Some(last) => seeked || changed_since(last, &mut info),
+            None => true,
+        };
+
+        if changed {
+            this.last = Some(info.clone());
+            Poll::Ready(Some(info))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RepeatMode;
+
+    #[test]
+    fn test_changed_since_skips_position() {
+        let last = MediaInfo {
+            title: String::from("Song"),
+            position: 1_000_000,
+            last_updated: 10,
+            ..Default::default()
+        };
+
+        let mut info = MediaInfo {
+            position: 1_100_000,
+            last_updated: 20,
+            ..last.clone()
+        };
+        assert!(!changed_since(&last, &mut info));
+        assert_eq!((info.position, info.last_updated), (1_100_000, 20));
+
+        info.repeat = RepeatMode::List;
+        assert!(changed_since(&last, &mut info));
+    }
+}
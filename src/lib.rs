@@ -1,15 +1,55 @@
+mod auto_update;
+mod builder;
+mod callback_queue;
+mod control_flags;
+#[cfg(feature = "image")]
+mod cover;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "stream")]
+mod info_stream;
+mod media_event;
 mod media_info;
+mod media_type;
+mod notify;
+mod platform;
+mod playback_capabilities;
 mod playback_state;
+#[cfg(feature = "stream")]
+mod position_stream;
+mod repeat_mode;
+mod session_handle;
+mod track_history;
 pub mod traits;
 mod utils;
 
 pub(crate) mod imp;
 mod media_session;
+mod media_snapshot;
 
-pub use error::Error;
+pub use auto_update::AutoUpdateHandle;
+pub use builder::MediaSessionBuilder;
+pub use callback_queue::CallbackQueue;
+pub use control_flags::ControlFlags;
+pub use error::{Error, ErrorKind};
+#[cfg(all(unix, not(target_os = "macos"), not(feature = "stub")))]
+pub use imp::DbusConnection;
+#[cfg(feature = "stream")]
+pub use info_stream::InfoStream;
+pub use media_event::MediaEvent;
 pub use media_info::{MediaInfo, PositionInfo};
-pub use media_session::MediaSession;
+pub use media_session::{now_playing, MediaSession};
+pub use media_snapshot::MediaSnapshot;
+pub use media_type::MediaType;
+pub use platform::Platform;
+pub use playback_capabilities::PlaybackCapabilities;
 pub use playback_state::PlaybackState;
+#[cfg(feature = "stream")]
+pub use position_stream::PositionStream;
+pub use repeat_mode::RepeatMode;
+pub use session_handle::SessionHandle;
+pub use track_history::TrackChange;
+pub use traits::{MediaSessionApp, MediaSessionBackend, MediaSessionControls};
 
 type Result<T> = core::result::Result<T, Error>;
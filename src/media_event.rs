@@ -0,0 +1,398 @@
+use std::sync::mpsc::Sender;
+#[cfg(feature = "stream")]
+use std::sync::{Arc, Mutex};
+
+use tracing::Level;
+
+#[cfg(feature = "stream")]
+use crate::position_stream::PositionAnchor;
+use crate::{
+    track_history::{TrackChange, TrackHistory},
+    utils::micros_since_epoch,
+    MediaInfo, MediaType, PlaybackState,
+};
+
+/// Position jumps larger than this (beyond normal playback) count as a seek
+const SEEK_THRESHOLD: i64 = 2_000_000;
+
+/// A change between two consecutive [`MediaInfo`] snapshots
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaEvent {
+    /// Title, artist or album changed
+    TrackChanged,
+    /// Playing / paused / stopped changed
+    PlaybackStateChanged,
+    /// Position jumped within the same track
+    Seeked,
+    /// Music / video / image changed (e.g. a browser tab switched to a video)
+    MediaTypeChanged,
+}
+
+macro_rules! event_at {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::error!($($arg)+),
+            Level::WARN => tracing::warn!($($arg)+),
+            Level::INFO => tracing::info!($($arg)+),
+            Level::DEBUG => tracing::debug!($($arg)+),
+            _ => tracing::trace!($($arg)+),
+        }
+    };
+}
+
+/// The parts of [`MediaInfo`] events are derived from (no cover data)
+struct Snapshot {
+    title: String,
+    artist: String,
+    album_title: String,
+    state: PlaybackState,
+    media_type: MediaType,
+    cover_hash: u64,
+    position: i64,
+    duration: i64,
+    playback_rate: f64,
+    position_stale: bool,
+    taken_at: i64,
+}
+
+impl Snapshot {
+    fn new(info: &MediaInfo) -> Self {
+        Self {
+            title: info.title.clone(),
+            artist: info.artist.clone(),
+            album_title: info.album_title.clone(),
+            state: info.state,
+            media_type: info.media_type,
+            cover_hash: info.cover_hash,
+            position: info.position,
+            duration: info.duration,
+            playback_rate: info.playback_rate,
+            position_stale: info.position_stale,
+            taken_at: micros_since_epoch(),
+        }
+    }
+
+    fn is_same_track(&self, info: &MediaInfo) -> bool {
+        self.title == info.title
+            && self.artist == info.artist
+            && self.album_title == info.album_title
+    }
+
+    /// Whether `info` shows the same track, state and cover (the position may differ)
+    fn is_same_display(&self, info: &MediaInfo) -> bool {
+        self.is_same_track(info) && self.state == info.state && self.cover_hash == info.cover_hash
+    }
+
+    /// Where the position should be now if nobody touched the player
+    fn expected_position(&self) -> i64 {
+        if self.state != PlaybackState::Playing {
+            return self.position;
+        }
+
+        let elapsed = (micros_since_epoch() - self.taken_at).max(0);
+
+        #[allow(clippy::cast_precision_loss, reason = "needed for multiplication")]
+        #[allow(clippy::cast_possible_truncation, reason = "rounded")]
+        let expected = self.position + (elapsed as f64 * self.playback_rate).round() as i64;
+
+        // A track sitting at its end stays there; live streams have no end
+        if self.duration > 0 {
+            expected.min(self.duration)
+        } else {
+            expected
+        }
+    }
+}
+
+type Callback = Box<dyn Fn(MediaInfo) + Send>;
+type EventCallback = Box<dyn Fn(MediaEvent, &MediaInfo) + Send>;
+type SessionChangedCallback = Box<dyn Fn(Option<String>) + Send>;
+
+/// Detects [`MediaEvent`]s across updates and hands them to the user
+pub(crate) struct EventDispatcher {
+    last: Snapshot,
+    callback: Option<Callback>,
+    change_callback: Option<Callback>,
+    event_callback: Option<EventCallback>,
+    session_changed_callback: Option<SessionChangedCallback>,
+    /// Feeds a [`CallbackQueue`](crate::CallbackQueue)
+    queue: Option<Sender<MediaInfo>>,
+    history: TrackHistory,
+    /// Read by every [`PositionStream`](crate::PositionStream)
+    #[cfg(feature = "stream")]
+    position: Arc<Mutex<PositionAnchor>>,
+}
+
+impl Default for EventDispatcher {
+    fn default() -> Self {
+        Self::with_history(0)
+    }
+}
+
+impl EventDispatcher {
+    /// Also keep the last `capacity` tracks (none for `0`)
+    pub fn with_history(capacity: usize) -> Self {
+        Self {
+            last: Snapshot::new(&MediaInfo::default()),
+            callback: None,
+            change_callback: None,
+            event_callback: None,
+            session_changed_callback: None,
+            queue: None,
+            history: TrackHistory::new(capacity),
+            #[cfg(feature = "stream")]
+            position: Arc::default(),
+        }
+    }
+
+    pub fn recent_tracks(&self) -> &[TrackChange] {
+        self.history.entries()
+    }
+
+    pub fn set_callback(&mut self, callback: Callback) {
+        self.callback = Some(callback);
+    }
+
+    pub fn set_change_callback(&mut self, callback: Callback) {
+        self.change_callback = Some(callback);
+    }
+
+    pub fn set_event_callback(&mut self, callback: EventCallback) {
+        self.event_callback = Some(callback);
+    }
+
+    pub fn set_session_changed_callback(&mut self, callback: SessionChangedCallback) {
+        self.session_changed_callback = Some(callback);
+    }
+
+    #[cfg(feature = "stream")]
+    pub fn position_anchor(&self) -> Arc<Mutex<PositionAnchor>> {
+        Arc::clone(&self.position)
+    }
+
+    pub fn set_queue(&mut self, queue: Sender<MediaInfo>) {
+        self.queue = Some(queue);
+    }
+
+    /// Report that the backend switched to the player `app_id` (`None`: no player left)
+    #[cfg_attr(any(feature = "stub", not(any(unix, windows))), allow(dead_code))]
+    pub fn session_changed(&self, app_id: Option<String>) {
+        tracing::info!(app_id, "Session changed");
+
+        if let Some(callback) = &self.session_changed_callback {
+            callback(app_id);
+        }
+    }
+
+    /// Compare `info` against the previous snapshot and remember it.
+    ///
+    /// Every detected event is logged at `log_level` (if set) and passed to the event
+    /// callback; the plain callback then receives `info` itself, and the change
+    /// callback too unless only the position moved. Returns the events.
+    pub fn dispatch(&mut self, info: MediaInfo, log_level: Option<Level>) -> Vec<MediaEvent> {
+        let events = self.detect(&info);
+        let changed = !self.last.is_same_display(&info);
+
+        if let Some(level) = log_level {
+            for event in &events {
+                self.log(level, *event, &info);
+            }
+        }
+
+        if let Some(callback) = &self.event_callback {
+            for event in &events {
+                callback(*event, &info);
+            }
+        }
+
+        self.last = Snapshot::new(&info);
+        self.history.record(&info);
+        #[cfg(feature = "stream")]
+        {
+            *self.position.lock().unwrap() = PositionAnchor::new(&info);
+        }
+
+        // Stop queueing once the queue is dropped
+        if let Some(queue) = &self.queue {
+            if queue.send(info.clone()).is_err() {
+                self.queue = None;
+            }
+        }
+
+        if let Some(callback) = self.change_callback.as_ref().filter(|_| changed) {
+            callback(info.clone());
+        }
+
+        if let Some(callback) = &self.callback {
+            callback(info);
+        }
+
+        events
+    }
+
+    fn detect(&self, info: &MediaInfo) -> Vec<MediaEvent> {
+        let mut events = Vec::new();
+
+        if !self.last.is_same_track(info) {
+            events.push(MediaEvent::TrackChanged);
+        }
+
+        if self.last.state != info.state {
+            events.push(MediaEvent::PlaybackStateChanged);
+        }
+
+        if self.last.media_type != info.media_type {
+            events.push(MediaEvent::MediaTypeChanged);
+        }
+
+        // A stale position is held while time goes on, so it says nothing about seeks
+        if events.is_empty()
+            && !info.is_stopped()
+            && !info.position_stale
+            && !self.last.position_stale
+            && (info.position - self.last.expected_position()).abs() > SEEK_THRESHOLD
+        {
+            events.push(MediaEvent::Seeked);
+        }
+
+        events
+    }
+
+    fn log(&self, level: Level, event: MediaEvent, info: &MediaInfo) {
+        let last = &self.last;
+
+        match event {
+            MediaEvent::TrackChanged => event_at!(
+                level,
+                event = "track_changed",
+                from.artist = last.artist,
+                from.title = last.title,
+                to.artist = info.artist,
+                to.title = info.title,
+                "Track changed"
+            ),
+            MediaEvent::PlaybackStateChanged => event_at!(
+                level,
+                event = "state_changed",
+                from = last.state.as_str(),
+                to = info.state.as_str(),
+                "Playback state changed"
+            ),
+            MediaEvent::Seeked => event_at!(
+                level,
+                event = "seeked",
+                from = last.expected_position(),
+                to = info.position,
+                "Seek detected"
+            ),
+            MediaEvent::MediaTypeChanged => event_at!(
+                level,
+                event = "media_type_changed",
+                from = last.media_type.as_str(),
+                to = info.media_type.as_str(),
+                "Media kind changed"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_change_callback_skips_position() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut events = EventDispatcher::default();
+        events.set_change_callback(Box::new({
+            let calls = Arc::clone(&calls);
+            move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+
+        let mut info = MediaInfo {
+            title: String::from("Song"),
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        for position in 0..10 {
+            info.position = position * 100_000;
+            events.dispatch(info.clone(), None);
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        info.cover_hash = 42;
+        events.dispatch(info.clone(), None);
+        info.state = PlaybackState::Paused;
+        events.dispatch(info, None);
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_expected_position_follows_rate() {
+        let mut snapshot = Snapshot::new(&MediaInfo {
+            state: PlaybackState::Playing,
+            position: 10_000_000,
+            duration: 200_000_000,
+            playback_rate: 1.25,
+            ..Default::default()
+        });
+        snapshot.taken_at -= 10_000_000;
+
+        let expected = snapshot.expected_position();
+        assert!((22_500_000..23_000_000).contains(&expected));
+
+        // Parked at the end of the track
+        snapshot.position = 200_000_000;
+        assert_eq!(snapshot.expected_position(), 200_000_000);
+    }
+
+    #[test]
+    fn test_slow_playback_is_not_seek() {
+        let mut events = EventDispatcher::default();
+        let mut info = MediaInfo {
+            title: String::from("Chapter"),
+            state: PlaybackState::Playing,
+            duration: 3_600_000_000,
+            position: 60_000_000,
+            playback_rate: 0.5,
+            ..Default::default()
+        };
+        events.dispatch(info.clone(), None);
+        events.last.taken_at -= 10_000_000;
+
+        info.position += 5_000_000;
+        assert!(events.detect(&info).is_empty());
+    }
+
+    #[test]
+    fn test_stale_position_is_not_seek() {
+        let mut events = EventDispatcher::default();
+        let info = MediaInfo {
+            title: String::from("Song"),
+            state: PlaybackState::Playing,
+            duration: 200_000_000,
+            position: 30_000_000,
+            position_stale: true,
+            ..Default::default()
+        };
+        events.dispatch(info.clone(), None);
+        events.last.taken_at -= 10_000_000;
+
+        assert!(events.detect(&info).is_empty());
+
+        // The first fresh report after a sleep lands anywhere
+        let fresh = MediaInfo {
+            position: 120_000_000,
+            position_stale: false,
+            ..info
+        };
+        assert!(events.detect(&fresh).is_empty());
+    }
+}
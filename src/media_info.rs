@@ -1,45 +1,144 @@
-use std::cmp::min;
+use std::time::Duration;
 
-use crate::{utils::micros_since_epoch, PlaybackState};
+use crate::{
+    builder::DEFAULT_MAX_EXTRAPOLATION,
+    utils::{duration_micros, fnv1a_64, micros_since_epoch, UNKNOWN_MIME},
+    MediaType, PlaybackState, RepeatMode,
+};
 
 #[derive(Clone)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaInfo {
     pub title: String,
     pub artist: String,
+    /// Every artist of the track; `artist` joins them with `, `
+    pub artists: Vec<String>,
 
     pub album_title: String,
     pub album_artist: String,
+    /// Stable id of the track: `mpris:trackid` on Linux, a hash of title, artist and
+    /// album on Windows (or when the player sends no id)
+    pub track_id: String,
+    pub genres: Vec<String>,
+    /// Position on the album, from 1
+    pub track_number: Option<u32>,
+    /// Number of tracks on the album (Windows only, MPRIS has no such key)
+    pub album_track_count: Option<u32>,
+    /// Tracks in the player's queue, from the MPRIS `TrackList` (Linux only): `None` when
+    /// the player has no tracklist
+    pub queue_length: Option<u32>,
+    /// `xesam:userRating`, `0.0..=1.0` (Linux only, GSMTC has no rating): `None` when the
+    /// player doesn't rate tracks
+    pub rating: Option<f64>,
 
     /// Microseconds
     pub duration: i64,
+    /// Earliest seekable position, microseconds: `0` unless the stream only keeps a
+    /// window of it (e.g. a DVR live buffer, Windows only)
+    pub seek_start: i64,
+    /// Latest seekable position, microseconds: `duration` unless the player reports a
+    /// seek window
+    pub seek_end: i64,
+    /// The track has no known length (live stream, unknown-length podcast): `position` is
+    /// reported as-is and there is no meaningful progress
+    pub is_live: bool,
     /// Microseconds since start
     pub position: i64,
+    /// When the player last reported the position, UNIX microseconds (`0` if never)
+    pub last_updated: i64,
+    /// The player's last report is older than the extrapolation cap (e.g. after a
+    /// system sleep), so `position` is the last reported one rather than extrapolated
+    pub position_stale: bool,
+    /// Playback speed, `1.0` at normal speed (e.g. `1.25` for a sped-up audiobook)
+    pub playback_rate: f64,
 
     pub cover_b64: String,
-    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    /// Not serialized (see `cover_b64`), empty after deserializing
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub cover_raw: Vec<u8>,
+    /// Hash of `cover_raw`, `0` when there is no cover. Unlike `cover_raw` it is
+    /// serialized, so after deserializing it still identifies the cover.
+    pub cover_hash: u64,
+    /// MIME type of `cover_raw`, `application/octet-stream` when unknown or no cover
+    pub cover_mime: String,
+
+    pub state: PlaybackState,
+
+    /// Player volume, `0.0..=1.0`, or `-1.0` when unknown (always on Windows, which
+    /// has no per-session volume): hide volume controls then
+    pub volume: f64,
 
-    pub state: String, // stopped, paused, playing
+    /// `None` when the player doesn't report shuffle
+    pub shuffle: Option<bool>,
+    pub repeat: RepeatMode,
+
+    pub media_type: MediaType,
+
+    /// The player owning the session: `SourceAppUserModelId` on Windows
+    /// (e.g. `Spotify.exe`), the MPRIS bus name on Linux
+    /// (e.g. `org.mpris.MediaPlayer2.spotify`)
+    pub source_app_id: String,
+}
+
+/// Where a track of `duration` in `state` is now, from the last reported position
+pub(crate) fn extrapolate_position(
+    state: PlaybackState,
+    duration: i64,
+    pos_info: &PositionInfo,
+) -> i64 {
+    match state {
+        PlaybackState::Stopped | PlaybackState::Closed => 0,
+        // Hold the last known position until the new timeline arrives
+        PlaybackState::Paused | PlaybackState::Changing | PlaybackState::Opening => {
+            pos_info.pos_raw
+        }
+        // Nothing to extrapolate towards
+        PlaybackState::Playing if duration <= 0 => pos_info.pos_raw,
+        // Most likely slept since: fast-forwarding would land at the end of the track
+        PlaybackState::Playing if pos_info.is_stale() => pos_info.pos_raw,
+        PlaybackState::Playing => {
+            // A timestamp in the future (clock skew) must not rewind the position
+            let update_delta = (micros_since_epoch() - pos_info.pos_last_update).max(0);
+
+            #[allow(clippy::cast_precision_loss, reason = "needed for multiplication")]
+            let track_delta = update_delta as f64 * pos_info.playback_rate;
+
+            #[allow(clippy::cast_possible_truncation, reason = "rounded")]
+            (pos_info.pos_raw + track_delta.round() as i64).clamp(0, duration.max(0))
+        }
+    }
 }
 
 impl MediaInfo {
+    pub(crate) fn extrapolated_position(&self, pos_info: &PositionInfo) -> i64 {
+        extrapolate_position(self.state, self.duration, pos_info)
+    }
+
     fn apply_position(&mut self, pos_info: &PositionInfo) {
-        let position = match PlaybackState::from(self.state.as_ref()) {
-            PlaybackState::Stopped => 0,
-            PlaybackState::Paused => pos_info.pos_raw,
-            PlaybackState::Playing => {
-                let update_delta = micros_since_epoch() - pos_info.pos_last_update;
+        self.position = self.extrapolated_position(pos_info);
+        self.last_updated = pos_info.pos_last_update;
+        self.playback_rate = pos_info.playback_rate;
+        self.position_stale = self.state == PlaybackState::Playing && pos_info.is_stale();
+        self.is_live = self.has_unknown_length();
+    }
 
-                #[allow(clippy::cast_precision_loss, reason = "needed for multiplication")]
-                let track_delta = update_delta as f64 * pos_info.playback_rate;
+    /// A track is loaded but its length isn't known
+    pub(crate) fn has_unknown_length(&self) -> bool {
+        self.duration <= 0 && !self.title.is_empty()
+    }
 
-                #[allow(clippy::cast_possible_truncation, reason = "rounded")]
-                min(self.duration, pos_info.pos_raw + track_delta.round() as i64)
-            }
-        };
+    /// Switch to `state`, re-anchoring `pos_info` where needed.
+    ///
+    /// Entering [`PlaybackState::Changing`] freezes the position at its current
+    /// extrapolated value, so it doesn't jump to 0 between tracks.
+    #[cfg_attr(any(not(windows), feature = "stub"), allow(dead_code))]
+    pub(crate) fn set_state(&mut self, state: PlaybackState, pos_info: &mut PositionInfo) {
+        if matches!(state, PlaybackState::Changing) {
+            pos_info.pos_raw = self.extrapolated_position(pos_info);
+            pos_info.pos_last_update = micros_since_epoch();
+        }
 
-        self.position = position;
+        self.state = state;
     }
 
     /// Return a [`MediaInfo`] with updated position
@@ -49,6 +148,217 @@ impl MediaInfo {
         info.apply_position(pos_info);
         info
     }
+
+    /// All fields as `(name, value)` pairs, formatted for display.
+    ///
+    /// Cover data is left out.
+    #[must_use]
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("title", self.title.clone()),
+            ("artist", self.artist.clone()),
+            ("artists", self.artists.join(", ")),
+            ("album_title", self.album_title.clone()),
+            ("album_artist", self.album_artist.clone()),
+            ("track_id", self.track_id.clone()),
+            ("genres", self.genres.join(", ")),
+            (
+                "track_number",
+                self.track_number
+                    .map_or_else(String::new, |n| n.to_string()),
+            ),
+            (
+                "album_track_count",
+                self.album_track_count
+                    .map_or_else(String::new, |n| n.to_string()),
+            ),
+            (
+                "queue_length",
+                self.queue_length
+                    .map_or_else(String::new, |n| n.to_string()),
+            ),
+            (
+                "rating",
+                self.rating.map_or_else(String::new, |r| r.to_string()),
+            ),
+            ("duration", self.duration.to_string()),
+            ("seek_start", self.seek_start.to_string()),
+            ("seek_end", self.seek_end.to_string()),
+            ("is_live", self.is_live.to_string()),
+            ("position", self.position.to_string()),
+            ("last_updated", self.last_updated.to_string()),
+            ("position_stale", self.position_stale.to_string()),
+            ("playback_rate", self.playback_rate.to_string()),
+            ("state", self.state.to_string()),
+            ("volume", self.volume.to_string()),
+            (
+                "shuffle",
+                self.shuffle.map_or_else(String::new, |s| s.to_string()),
+            ),
+            ("repeat", self.repeat.to_string()),
+            ("media_type", self.media_type.to_string()),
+            ("source_app_id", self.source_app_id.clone()),
+            ("cover_hash", self.cover_hash.to_string()),
+            ("cover_mime", self.cover_mime.clone()),
+        ]
+    }
+
+    /// Same as [`MediaInfo::fields`], but only the fields that differ from
+    /// [`MediaInfo::default`]
+    #[must_use]
+    pub fn non_default_fields(&self) -> Vec<(&'static str, String)> {
+        let default = Self::default().fields();
+
+        self.fields()
+            .into_iter()
+            .zip(default)
+            .filter(|(field, default)| field != default)
+            .map(|(field, _)| field)
+            .collect()
+    }
+
+    /// Whether the cover differs from the one identified by `prev_hash`
+    /// (a previously seen `cover_hash`).
+    ///
+    /// Lets IPC consumers send the cover only when it changes.
+    #[must_use]
+    pub fn cover_changed_since(&self, prev_hash: u64) -> bool {
+        self.cover_hash != prev_hash
+    }
+
+    /// The cover as a `data:` URI (e.g. `data:image/png;base64,...`) for web views,
+    /// typed with `cover_mime`; `None` without a cover
+    #[must_use]
+    pub fn cover_data_uri(&self) -> Option<String> {
+        if self.cover_b64.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "data:{};base64,{}",
+            self.cover_mime, self.cover_b64
+        ))
+    }
+
+    /// `position` as a [`Duration`], zero if negative
+    #[must_use]
+    pub fn position_duration(&self) -> Duration {
+        Duration::from_micros(self.position.try_into().unwrap_or_default())
+    }
+
+    /// `duration` (track length) as a [`Duration`], zero if unknown
+    #[must_use]
+    pub fn total_duration(&self) -> Duration {
+        Duration::from_micros(self.duration.try_into().unwrap_or_default())
+    }
+
+    /// How far into the track playback is, `0.0..=1.0` (`0.0` when the length is unknown)
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        if self.duration <= 0 {
+            return 0.0;
+        }
+
+        #[allow(clippy::cast_precision_loss, reason = "a ratio")]
+        let progress = self.position as f64 / self.duration as f64;
+
+        progress.clamp(0.0, 1.0)
+    }
+
+    /// Whether there's a title or an artist to show. Players report both as empty
+    /// while switching tracks or when they have nothing to say, so a UI can show a
+    /// placeholder instead of a blank bar.
+    #[must_use]
+    pub fn has_metadata(&self) -> bool {
+        !self.title.trim().is_empty() || !self.artist.trim().is_empty()
+    }
+
+    /// Whether this session is playing or about to (switching tracks, loading), i.e.
+    /// counts towards [`MediaSession::active_count`](crate::MediaSession::active_count)
+    #[must_use]
+    pub fn is_active_session(&self) -> bool {
+        self.state.is_active()
+    }
+
+    /// [`progress`](Self::progress) in whole percent, `0..=100`, for progress bars
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "progress is clamped to 0.0..=1.0"
+    )]
+    pub fn progress_percent(&self) -> u8 {
+        (self.progress() * 100.0).round() as u8
+    }
+
+    /// Same as `state`
+    #[must_use]
+    pub fn playback_state(&self) -> PlaybackState {
+        self.state
+    }
+
+    #[must_use]
+    pub fn is_playing(&self) -> bool {
+        self.state == PlaybackState::Playing
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.state == PlaybackState::Paused
+    }
+
+    /// One-line JSON of every field, as in the `From<MediaInfo> for JsonValue` impl
+    #[cfg(feature = "json")]
+    #[must_use]
+    pub fn to_json_string(&self) -> String {
+        json::JsonValue::from(self.clone()).dump()
+    }
+
+    /// Stopped or closed: nothing will play without user action
+    #[must_use]
+    pub fn is_stopped(&self) -> bool {
+        matches!(self.state, PlaybackState::Stopped | PlaybackState::Closed)
+    }
+
+    /// Whether `other` is the same track, by [`MediaInfo::track_id`]
+    #[must_use]
+    pub fn is_same_track(&self, other: &Self) -> bool {
+        self.track_id == other.track_id
+    }
+
+    /// `track_id` for players without one, derived from title, artist and album
+    #[cfg_attr(any(feature = "stub", not(any(unix, windows))), allow(dead_code))]
+    pub(crate) fn metadata_track_id(&self) -> String {
+        let key = [&self.title, &self.artist, &self.album_title].map(String::as_str);
+
+        format!("{:016x}", fnv1a_64(key.join("\0").as_bytes()))
+    }
+
+    /// Best-effort guess whether the current "track" is an advertisement.
+    ///
+    /// Deliberately conservative, and Spotify only: matches Spotify's ad track ids
+    /// (`spotify:ad:...`), or metadata from a Spotify [`source_app_id`] with no album
+    /// and the `"Advertisement"` title or `"Spotify"` artist it uses for ads. Expect
+    /// false negatives rather than false positives.
+    ///
+    /// [`source_app_id`]: Self::source_app_id
+    #[must_use]
+    pub fn looks_like_ad(&self) -> bool {
+        const AD_TRACK_IDS: &[&str] = &["spotify:ad:", "/com/spotify/ad/"];
+
+        if AD_TRACK_IDS.iter().any(|id| self.track_id.contains(id)) {
+            return true;
+        }
+
+        // Spotify.exe, org.mpris.MediaPlayer2.spotify, com.spotify.client, ...
+        let from_spotify = self.source_app_id.to_lowercase().contains("spotify");
+        if !from_spotify || !self.album_title.trim().is_empty() {
+            return false;
+        }
+
+        self.title.trim().eq_ignore_ascii_case("advertisement")
+            || self.artist.trim().eq_ignore_ascii_case("spotify")
+    }
 }
 
 #[cfg(feature = "json")]
@@ -57,12 +367,32 @@ impl From<MediaInfo> for json::JsonValue {
         json::object! {
             title: info.title,
             artist: info.artist,
+            artists: info.artists,
             album_title: info.album_title,
             album_artist: info.album_artist,
+            track_id: info.track_id,
+            genres: info.genres,
+            track_number: info.track_number,
+            album_track_count: info.album_track_count,
+            queue_length: info.queue_length,
+            rating: info.rating,
             duration: info.duration,
+            seek_start: info.seek_start,
+            seek_end: info.seek_end,
+            is_live: info.is_live,
             position: info.position,
+            last_updated: info.last_updated,
+            position_stale: info.position_stale,
+            playback_rate: info.playback_rate,
             cover_b64: info.cover_b64,
-            state: info.state,
+            cover_hash: info.cover_hash,
+            cover_mime: info.cover_mime,
+            state: info.state.as_str(),
+            volume: info.volume,
+            shuffle: info.shuffle,
+            repeat: info.repeat.as_str(),
+            media_type: info.media_type.as_str(),
+            source_app_id: info.source_app_id,
         }
     }
 }
@@ -72,17 +402,40 @@ impl Default for MediaInfo {
         Self {
             title: String::new(),
             artist: String::new(),
+            artists: Vec::new(),
 
             album_title: String::new(),
             album_artist: String::new(),
+            track_id: String::new(),
+            genres: Vec::new(),
+            track_number: None,
+            album_track_count: None,
+            queue_length: None,
+            rating: None,
 
             duration: 0,
+            seek_start: 0,
+            seek_end: 0,
+            is_live: false,
             position: 0,
+            last_updated: 0,
+            position_stale: false,
+            playback_rate: 1.0,
 
             cover_b64: String::new(),
             cover_raw: Vec::new(),
+            cover_hash: 0,
+            cover_mime: String::from(UNKNOWN_MIME),
+
+            state: PlaybackState::Stopped,
+            volume: -1.0,
+
+            shuffle: None,
+            repeat: RepeatMode::None,
 
-            state: PlaybackState::Stopped.into(),
+            media_type: MediaType::Unknown,
+
+            source_app_id: String::new(),
         }
     }
 }
@@ -103,12 +456,32 @@ impl std::fmt::Debug for MediaInfo {
         struct MediaInfo<'a> {
             title: &'a str,
             artist: &'a str,
+            artists: &'a [String],
             album_title: &'a str,
             album_artist: &'a str,
+            track_id: &'a str,
+            genres: &'a [String],
+            track_number: &'a Option<u32>,
+            album_track_count: &'a Option<u32>,
+            queue_length: &'a Option<u32>,
+            rating: &'a Option<f64>,
             duration: &'a i64,
+            seek_start: &'a i64,
+            seek_end: &'a i64,
+            is_live: &'a bool,
             position: &'a i64,
-            state: &'a str,
+            last_updated: &'a i64,
+            position_stale: &'a bool,
+            playback_rate: &'a f64,
+            state: &'a PlaybackState,
+            volume: &'a f64,
+            shuffle: &'a Option<bool>,
+            repeat: &'a RepeatMode,
+            media_type: &'a MediaType,
+            source_app_id: &'a str,
 
+            cover_hash: &'a u64,
+            cover_mime: &'a str,
             cover_b64: Field<'a>,
             cover_raw: Field<'a>,
         }
@@ -116,12 +489,32 @@ impl std::fmt::Debug for MediaInfo {
         let Self {
             title,
             artist,
+            artists,
             album_title,
             album_artist,
+            track_id,
+            genres,
+            track_number,
+            album_track_count,
+            queue_length,
+            rating,
             duration,
+            seek_start,
+            seek_end,
+            is_live,
             position,
+            last_updated,
+            position_stale,
+            playback_rate,
             state,
+            volume,
+            shuffle,
+            repeat,
+            media_type,
+            source_app_id,
 
+            cover_hash,
+            cover_mime,
             cover_raw: cr,
             cover_b64: c64,
         } = self;
@@ -130,12 +523,32 @@ impl std::fmt::Debug for MediaInfo {
             &MediaInfo {
                 title,
                 artist,
+                artists,
                 album_title,
                 album_artist,
+                track_id,
+                genres,
+                track_number,
+                album_track_count,
+                queue_length,
+                rating,
                 duration,
+                seek_start,
+                seek_end,
+                is_live,
                 position,
+                last_updated,
+                position_stale,
+                playback_rate,
                 state,
+                volume,
+                shuffle,
+                repeat,
+                media_type,
+                source_app_id,
 
+                cover_hash,
+                cover_mime,
                 cover_raw: Field {
                     inner: if cr.is_empty() { "<none>" } else { "<...>" },
                 },
@@ -149,11 +562,89 @@ impl std::fmt::Debug for MediaInfo {
     }
 }
 
+/// Compares every field, but the cover only by `cover_hash` and `cover_mime`: the
+/// potentially large `cover_raw` and `cover_b64` are skipped, as they follow the hash.
+impl PartialEq for MediaInfo {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructured so a new field can't be forgotten here
+        let Self {
+            title,
+            artist,
+            artists,
+            album_title,
+            album_artist,
+            track_id,
+            genres,
+            track_number,
+            album_track_count,
+            queue_length,
+            rating,
+            duration,
+            seek_start,
+            seek_end,
+            is_live,
+            position,
+            last_updated,
+            position_stale,
+            playback_rate,
+            cover_b64: _,
+            cover_raw: _,
+            cover_hash,
+            cover_mime,
+            state,
+            volume,
+            shuffle,
+            repeat,
+            media_type,
+            source_app_id,
+        } = self;
+
+        *title == other.title
+            && *artist == other.artist
+            && *artists == other.artists
+            && *album_title == other.album_title
+            && *album_artist == other.album_artist
+            && *track_id == other.track_id
+            && *genres == other.genres
+            && *track_number == other.track_number
+            && *album_track_count == other.album_track_count
+            && *queue_length == other.queue_length
+            && *rating == other.rating
+            && *duration == other.duration
+            && *seek_start == other.seek_start
+            && *seek_end == other.seek_end
+            && *is_live == other.is_live
+            && *position == other.position
+            && *last_updated == other.last_updated
+            && *position_stale == other.position_stale
+            && *playback_rate == other.playback_rate
+            && *cover_hash == other.cover_hash
+            && *cover_mime == other.cover_mime
+            && *state == other.state
+            && *volume == other.volume
+            && *shuffle == other.shuffle
+            && *repeat == other.repeat
+            && *media_type == other.media_type
+            && *source_app_id == other.source_app_id
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PositionInfo {
     pub playback_rate: f64,
     pub pos_last_update: i64,
     pub pos_raw: i64,
+    /// Microseconds since `pos_last_update` beyond which `pos_raw` is held instead of
+    /// extrapolated
+    pub max_extrapolation: i64,
+}
+
+impl PositionInfo {
+    /// The report is more than `max_extrapolation` old: it most likely predates a sleep
+    /// rather than playback
+    pub(crate) fn is_stale(&self) -> bool {
+        micros_since_epoch() - self.pos_last_update > self.max_extrapolation
+    }
 }
 
 impl Default for PositionInfo {
@@ -162,6 +653,259 @@ impl Default for PositionInfo {
             playback_rate: 1.0,
             pos_last_update: 0,
             pos_raw: 0,
+            max_extrapolation: duration_micros(DEFAULT_MAX_EXTRAPOLATION),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let raw = vec![0x89, b'P', b'N', b'G'];
+        let info = MediaInfo {
+            title: "Title".to_owned(),
+            artists: vec!["Artist".to_owned(), "Guest".to_owned()],
+            track_number: Some(3),
+            rating: Some(0.8),
+            duration: 180_000_000,
+            position: 60_000_000,
+            playback_rate: 1.25,
+            cover_b64: "iVBORw==".to_owned(),
+            cover_hash: crate::utils::fnv1a_64(&raw),
+            cover_mime: "image/png".to_owned(),
+            cover_raw: raw,
+            state: PlaybackState::Playing,
+            shuffle: Some(true),
+            repeat: RepeatMode::Track,
+            media_type: MediaType::Music,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let back: MediaInfo = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, info);
+        assert!(back.cover_raw.is_empty());
+        assert_eq!(back.cover_hash, info.cover_hash);
+    }
+
+    #[test]
+    fn test_cover_data_uri() {
+        let mut info = MediaInfo::default();
+        assert_eq!(info.cover_data_uri(), None);
+
+        info.cover_b64 = "iVBORw0KGgo=".to_owned();
+        info.cover_mime = "image/png".to_owned();
+        assert_eq!(
+            info.cover_data_uri().as_deref(),
+            Some("data:image/png;base64,iVBORw0KGgo=")
+        );
+    }
+
+    #[test]
+    fn test_eq_compares_cover_by_hash() {
+        let info = MediaInfo {
+            title: "Title".to_owned(),
+            cover_raw: vec![1, 2, 3],
+            cover_hash: 42,
+            ..Default::default()
+        };
+
+        let mut other = MediaInfo {
+            cover_raw: Vec::new(),
+            ..info.clone()
+        };
+        assert_eq!(info, other);
+
+        other.cover_hash = 43;
+        assert_ne!(info, other);
+
+        other.cover_hash = 42;
+        other.position = 1;
+        assert_ne!(info, other);
+    }
+
+    #[test]
+    fn test_looks_like_ad() {
+        let spotify = MediaInfo {
+            source_app_id: String::from("org.mpris.MediaPlayer2.spotify"),
+            ..Default::default()
+        };
+
+        let ad = MediaInfo {
+            title: String::from("Advertisement"),
+            ..spotify.clone()
+        };
+        assert!(ad.looks_like_ad());
+
+        let ad_id = MediaInfo {
+            title: String::from("Sponsored"),
+            track_id: String::from("spotify:ad:0000000000000000"),
+            ..Default::default()
+        };
+        assert!(ad_id.looks_like_ad());
+
+        let song = MediaInfo {
+            title: String::from("Advertisement"),
+            album_title: String::from("Album"),
+            ..spotify
+        };
+        assert!(!song.looks_like_ad());
+
+        // Other players' tracks are never ads, whatever their title or artist
+        let other = MediaInfo {
+            title: String::from("Ad"),
+            artist: String::from("Spotify"),
+            source_app_id: String::from("org.mpris.MediaPlayer2.mpv"),
+            ..Default::default()
+        };
+        assert!(!other.looks_like_ad());
+    }
+
+    #[test]
+    fn test_has_metadata() {
+        let mut info = MediaInfo::default();
+        assert!(!info.has_metadata());
+
+        info.title = " ".to_owned();
+        assert!(!info.has_metadata());
+
+        info.artist = "Artist".to_owned();
+        assert!(info.has_metadata());
+    }
+
+    #[test]
+    fn test_changing_holds_position() {
+        let mut info = MediaInfo {
+            duration: 200_000_000,
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        let mut pos_info = PositionInfo {
+            pos_raw: 30_000_000,
+            pos_last_update: micros_since_epoch() - 10_000_000,
+            ..Default::default()
+        };
+
+        let before = info.with_position(&pos_info).position;
+        info.set_state(PlaybackState::Changing, &mut pos_info);
+        let after = info.with_position(&pos_info).position;
+
+        assert_ne!(after, 0);
+        assert!((after - before).abs() < 1_000_000);
+    }
+
+    #[test]
+    fn test_future_update_holds_position() {
+        let info = MediaInfo {
+            duration: 200_000_000,
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        let pos_info = PositionInfo {
+            pos_raw: 30_000_000,
+            pos_last_update: micros_since_epoch() + 60_000_000,
+            ..Default::default()
+        };
+
+        assert_eq!(info.with_position(&pos_info).position, 30_000_000);
+    }
+
+    #[test]
+    fn test_sleep_gap_holds_position() {
+        let info = MediaInfo {
+            duration: 200_000_000,
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        let pos_info = PositionInfo {
+            pos_raw: 30_000_000,
+            pos_last_update: micros_since_epoch() - 3_600_000_000,
+            ..Default::default()
+        };
+
+        let info = info.with_position(&pos_info);
+        assert_eq!(info.position, 30_000_000);
+        assert!(info.position_stale);
+    }
+
+    #[test]
+    fn test_sleep_gap_mid_track_holds_position() {
+        // An hour asleep in the middle of a 3 hour audiobook
+        let info = MediaInfo {
+            duration: 3 * 3_600_000_000,
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        let pos_info = PositionInfo {
+            pos_raw: 30_000_000,
+            pos_last_update: micros_since_epoch() - 3_600_000_000,
+            ..Default::default()
+        };
+
+        let info = info.with_position(&pos_info);
+        assert_eq!(info.position, 30_000_000);
+        assert!(info.position_stale);
+    }
+
+    #[test]
+    fn test_unknown_length_is_live() {
+        let info = MediaInfo {
+            title: String::from("Radio"),
+            duration: 0,
+            state: PlaybackState::Playing,
+            ..Default::default()
+        };
+        let pos_info = PositionInfo {
+            pos_raw: 30_000_000,
+            pos_last_update: micros_since_epoch() - 10_000_000,
+            ..Default::default()
+        };
+
+        let info = info.with_position(&pos_info);
+        assert!(info.is_live);
+        assert_eq!(info.position, 30_000_000);
+        assert!(info.progress().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_zero_duration_percent() {
+        let info = MediaInfo {
+            position: 30_000_000,
+            duration: 0,
+            ..Default::default()
+        };
+        assert_eq!(info.progress_percent(), 0);
+
+        let past_end = MediaInfo {
+            position: 300_000_000,
+            duration: 200_000_000,
+            ..Default::default()
+        };
+        assert_eq!(past_end.progress_percent(), 100);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_string() {
+        let info = MediaInfo {
+            title: "Song".to_owned(),
+            state: PlaybackState::Playing,
+            playback_rate: 1.5,
+            shuffle: Some(true),
+            repeat: RepeatMode::List,
+            ..Default::default()
+        };
+
+        let parsed = json::parse(&info.to_json_string()).unwrap();
+        assert_eq!(parsed["title"], "Song");
+        assert_eq!(parsed["state"], "playing");
+        assert_eq!(parsed["playback_rate"], 1.5);
+        assert_eq!(parsed["shuffle"], true);
+        assert_eq!(parsed["repeat"], "list");
+    }
+}
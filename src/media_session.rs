@@ -1 +1,278 @@
+use std::{io, path::Path, task::Poll, time::Duration};
+
 pub use crate::imp::MediaSession;
+
+use crate::{
+    CallbackQueue, MediaEvent, MediaInfo, MediaSessionBuilder, MediaSnapshot, PlaybackState,
+    TrackChange,
+};
+
+/// What is playing right now, without keeping a session around: `None` when there is
+/// no player.
+///
+/// Creates a session, updates it once and drops it, so prefer a [`MediaSession`] for
+/// anything repeated.
+pub fn now_playing() -> crate::Result<Option<MediaInfo>> {
+    let mut session = MediaSession::new()?;
+    session.update();
+
+    Ok(session.is_available().then(|| session.get_info()))
+}
+
+impl MediaSession {
+    #[must_use]
+    pub fn builder() -> MediaSessionBuilder {
+        MediaSessionBuilder::new()
+    }
+
+    /// Shorthand for a session built with [`MediaSessionBuilder::priority`]: the first
+    /// running player matching an entry of `names` (e.g. `spotify`, `mpv`) wins
+    pub fn with_preferred(names: &[&str]) -> crate::Result<Self> {
+        Self::builder().priority(names.iter().copied()).build()
+    }
+
+    /// Shorthand for [`MediaSessionBuilder::spawn_auto_update`] with default options:
+    /// `update` on a background thread every `interval`, with each fresh [`MediaInfo`]
+    /// sent to the returned receiver
+    pub fn spawn_auto_update(
+        interval: Duration,
+    ) -> crate::Result<(
+        crate::AutoUpdateHandle,
+        std::sync::mpsc::Receiver<MediaInfo>,
+    )> {
+        Self::builder().spawn_auto_update(interval)
+    }
+
+    /// Let go of the player now instead of on drop, e.g. when the app goes to the
+    /// background: event handlers are removed, the connection is dropped and (on
+    /// Windows) the session's own tokio runtime is shut down.
+    ///
+    /// The session stays usable but empty: `update` does nothing, `get_info` returns a
+    /// default [`MediaInfo`] and `select_session` fails. Build a new session to resume.
+    pub fn close(&mut self) {
+        self.close_impl();
+    }
+
+    /// Fail controls that take longer than `timeout` with
+    /// [`ErrorKind::Timeout`](crate::ErrorKind::Timeout), so a wedged player can't hang
+    /// the caller (default 5s).
+    ///
+    /// On Linux this bounds every call to the player, reads included. Not enforced on
+    /// macOS.
+    pub fn set_control_timeout(&mut self, timeout: Duration) {
+        self.set_control_timeout_impl(timeout);
+    }
+
+    /// Skip reading covers when `fetch` is `false`, leaving the cover fields empty:
+    /// saves decoding and base64-encoding every thumbnail when only the metadata is
+    /// used. Takes effect on the next `update`.
+    ///
+    /// [`write_cover_to`](Self::write_cover_to) still reads the cover on demand on
+    /// Linux. Covers are never read on macOS.
+    pub fn set_fetch_cover(&mut self, fetch: bool) {
+        self.set_fetch_cover_impl(fetch);
+    }
+
+    /// Stop extrapolating the position once the player's last report is more than `max`
+    /// old (default 5s): the last reported position is kept and
+    /// [`MediaInfo::position_stale`] set, instead of jumping ahead (often to the end of
+    /// the track) after e.g. a system sleep.
+    ///
+    /// Linux and macOS re-read the position on every `update`. On Windows some players
+    /// only report it on seeks and pauses, so raise `max` to cover their longest gap.
+    pub fn set_max_extrapolation(&mut self, max: Duration) {
+        self.set_max_extrapolation_impl(max);
+    }
+
+    /// Call `callback` with the fresh [`MediaInfo`] after every `update`.
+    ///
+    /// Like every callback here, it runs on the thread calling `update` (or polling
+    /// [`info_stream`](Self::info_stream)), on all platforms; platform event threads
+    /// never call it. To handle updates on another thread, use
+    /// [`set_callback_send`](Self::set_callback_send).
+    pub fn set_callback<F: Fn(MediaInfo) + Send + 'static>(&mut self, callback: F) {
+        self.events_mut().set_callback(Box::new(callback));
+    }
+
+    /// Like [`set_callback`](Self::set_callback), but only when the title, artist,
+    /// album, state or cover changed since the last `update`: position-only updates
+    /// are skipped, for work worth doing once per track (e.g. a rich presence update).
+    pub fn set_callback_on_change<F: Fn(MediaInfo) + Send + 'static>(&mut self, callback: F) {
+        self.events_mut().set_change_callback(Box::new(callback));
+    }
+
+    /// Hand every fresh [`MediaInfo`] to `callback` on another thread: each `update`
+    /// queues it, and the returned [`CallbackQueue`] (which is `Send`) runs `callback`
+    /// for the queued ones on [`poll_callbacks`](CallbackQueue::poll_callbacks).
+    ///
+    /// Replaces the queue from an earlier call.
+    pub fn set_callback_send<F: Fn(MediaInfo) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) -> CallbackQueue {
+        let (sender, queue) = CallbackQueue::new(Box::new(callback));
+        self.events_mut().set_queue(sender);
+        queue
+    }
+
+    /// Call `callback` for every [`MediaEvent`] detected on `update`, along with the
+    /// [`MediaInfo`] it produced
+    pub fn set_event_callback<F: Fn(MediaEvent, &MediaInfo) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) {
+        self.events_mut().set_event_callback(Box::new(callback));
+    }
+
+    /// [`MediaInfo`] updates as a [`Stream`](futures_core::Stream), instead of calling
+    /// `update` on a timer.
+    ///
+    /// Yields the current state right away, then whenever anything but the
+    /// extrapolated position changes (shuffle, volume, a late cover, ...) or the
+    /// player seeks.
+    /// Windows wakes the stream from session events; on Linux the player is re-checked
+    /// periodically. Polling the stream runs `update`, so callbacks still fire.
+    #[cfg(feature = "stream")]
+    pub fn info_stream(&mut self) -> crate::InfoStream<'_> {
+        crate::InfoStream::new(self)
+    }
+
+    /// The extrapolated position in microseconds every `interval`, e.g. to animate a
+    /// progress bar without copying the whole [`MediaInfo`] each frame.
+    ///
+    /// Follows the state and position seen on every `update` (or
+    /// [`info_stream`](Self::info_stream) item) and extrapolates in between. Doesn't
+    /// borrow the session; ends when the session is dropped. `interval` is at least
+    /// 1ms.
+    #[cfg(feature = "stream")]
+    #[must_use]
+    pub fn position_stream(&self, interval: Duration) -> crate::PositionStream {
+        crate::PositionStream::new(
+            self.events().position_anchor(),
+            interval,
+            self.position_info().max_extrapolation,
+        )
+    }
+
+    /// Wait until a player is playing something (or at least has a title), updating as
+    /// it goes, for "what's playing" at startup without polling `update` yourself.
+    ///
+    /// Woken by session events on Windows; on Linux the bus is re-checked for a player
+    /// every 250ms, and only read once one shows up. Fails with
+    /// [`ErrorKind::Timeout`](crate::ErrorKind::Timeout) once `timeout` elapses.
+    /// Doesn't need the `stream` feature, and any executor can drive it.
+    ///
+    /// On Windows, await it from a tokio runtime only with
+    /// [`use_existing_runtime`](MediaSessionBuilder::use_existing_runtime).
+    pub async fn wait_for_session(&mut self, timeout: Option<Duration>) -> crate::Result<()> {
+        let notify = self.notifier();
+        let deadline = timeout.map(|timeout| {
+            notify.wake_after(timeout);
+            std::time::Instant::now() + timeout
+        });
+
+        std::future::poll_fn(|cx| {
+            // Register before updating, so an event landing in between isn't lost
+            notify.register(cx.waker());
+
+            let found = self.session_appeared() && {
+                self.update();
+                self.current_info()
+                    .is_some_and(|info| !info.title.is_empty() || !info.is_stopped())
+            };
+
+            if found {
+                Poll::Ready(Ok(()))
+            } else if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+                Poll::Ready(Err(crate::Error::with_kind(
+                    crate::ErrorKind::Timeout,
+                    "no session before the timeout",
+                )))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Save the current cover as-is (see `cover_mime` for the format) to `path`.
+    ///
+    /// Fails with [`io::ErrorKind::NotFound`] when there is no cover, instead of
+    /// writing an empty file.
+    pub fn write_thumbnail(&self, path: &Path) -> io::Result<()> {
+        let cover = self.get_info().cover_raw;
+
+        if cover.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no cover"));
+        }
+
+        std::fs::write(path, cover)
+    }
+
+    /// The extrapolated position in microseconds, without copying the whole
+    /// [`MediaInfo`] (cover included) like `get_info` does.
+    ///
+    /// Cheap enough to call every frame.
+    #[must_use]
+    pub fn get_position(&self) -> i64 {
+        self.current_info()
+            .map_or(0, |info| info.extrapolated_position(&self.position_info()))
+    }
+
+    /// The playback state, without copying the whole [`MediaInfo`]
+    #[must_use]
+    pub fn get_state(&self) -> PlaybackState {
+        self.current_info()
+            .map_or_else(PlaybackState::default, |info| info.state)
+    }
+
+    /// A `Send` copy of the current state, for reading from other threads
+    #[must_use]
+    pub fn snapshot(&self) -> MediaSnapshot {
+        MediaSnapshot::new(self.get_info(), self.position_info())
+    }
+
+    /// Tracks seen on `update`, oldest first, once enabled with
+    /// [`MediaSessionBuilder::track_history`]; empty otherwise
+    #[must_use]
+    pub fn recent_tracks(&self) -> &[TrackChange] {
+        self.events().recent_tracks()
+    }
+
+    /// How many players are playing (or about to) right now, the selected one included.
+    ///
+    /// More than one means the selection may flip between them: warn, or pin one with
+    /// `select_session`. Queries every player, so don't call it on every frame.
+    #[must_use]
+    pub fn active_count(&self) -> usize {
+        self.session_states()
+            .into_iter()
+            .filter(|state| state.is_active())
+            .count()
+    }
+
+    /// Call `callback` with the new source app id when the backend switches players,
+    /// or `None` when the last one closes
+    pub fn set_session_changed_callback<F: Fn(Option<String>) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) {
+        self.events_mut()
+            .set_session_changed_callback(Box::new(callback));
+    }
+
+    /// Current cover scaled down to fit in a `max_dim` square, encoded as PNG.
+    ///
+    /// The last result is cached, so calling this on every update is cheap.
+    #[cfg(feature = "image")]
+    #[must_use]
+    pub fn cover_at(&self, max_dim: u32) -> Option<Vec<u8>> {
+        // Borrowed rather than through `get_info`, which copies the cover
+        let info = self
+            .current_info()
+            .filter(|info| !self.config().hides(info))?;
+
+        self.resize_cache()
+            .get(&info.cover_raw, info.cover_hash, max_dim)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{MediaInfo, PositionInfo};
+
+/// What the session knew at its last `update`, detached from it.
+///
+/// Unlike [`MediaSession`](crate::MediaSession) this is `Clone + Send + Sync`, so it can
+/// be handed to another thread (e.g. for rendering) that calls [`info_now`](Self::info_now)
+/// as often as it likes. Take a new one from
+/// [`MediaSession::snapshot`](crate::MediaSession::snapshot) after each `update`.
+#[derive(Clone, Debug, Default)]
+pub struct MediaSnapshot {
+    info: MediaInfo,
+    pos_info: PositionInfo,
+}
+
+impl MediaSnapshot {
+    pub(crate) fn new(info: MediaInfo, pos_info: PositionInfo) -> Self {
+        Self { info, pos_info }
+    }
+
+    /// The info with its position extrapolated to now
+    #[must_use]
+    pub fn info_now(&self) -> MediaInfo {
+        self.info.with_position(&self.pos_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::micros_since_epoch, PlaybackState};
+
+    #[test]
+    fn test_extrapolated_on_read() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let snapshot = MediaSnapshot::new(
+            MediaInfo {
+                title: "Song".to_owned(),
+                duration: 60_000_000,
+                state: PlaybackState::Playing,
+                ..Default::default()
+            },
+            PositionInfo {
+                pos_raw: 1_000_000,
+                pos_last_update: micros_since_epoch() - 2_000_000,
+                ..Default::default()
+            },
+        );
+        assert_send_sync(&snapshot);
+
+        let position = snapshot.clone().info_now().position;
+        assert!((3_000_000..3_500_000).contains(&position));
+    }
+}
@@ -0,0 +1,195 @@
+#![allow(
+    dead_code,
+    reason = "each backend uses a different part, streams the rest"
+)]
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{Arc, Condvar, Mutex, OnceLock, Weak},
+    task::Waker,
+    time::{Duration, Instant},
+};
+
+/// Shortest ticker interval: a zero one would wake its stream in a busy loop
+pub const MIN_TICK: Duration = Duration::from_millis(1);
+
+type Slot = Mutex<Option<Waker>>;
+
+/// Wakes a pending [`InfoStream`](crate::info_stream::InfoStream) when a backend has
+/// something new
+#[derive(Clone, Default)]
+pub(crate) struct Notify {
+    waker: Arc<Slot>,
+}
+
+impl Notify {
+    pub fn register(&self, waker: &Waker) {
+        let mut slot = self.waker.lock().unwrap();
+
+        if !slot.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            *slot = Some(waker.clone());
+        }
+    }
+
+    pub fn wake(&self) {
+        wake(&self.waker);
+    }
+
+    /// Woken every `interval` (at least [`MIN_TICK`]) until every clone is dropped, for
+    /// backends that poll
+    pub fn ticker(interval: Duration) -> Self {
+        let notify = Self::default();
+        timer().schedule(&notify, Instant::now(), Some(interval.max(MIN_TICK)));
+        notify
+    }
+
+    /// Wake once after `delay`, for deadlines between backend wakeups; forgotten if
+    /// every clone is dropped first
+    pub fn wake_after(&self, delay: Duration) {
+        timer().schedule(self, Instant::now() + delay, None);
+    }
+}
+
+fn wake(slot: &Slot) {
+    if let Some(waker) = slot.lock().unwrap().take() {
+        waker.wake();
+    }
+}
+
+/// A wakeup due at `due`, repeated every `interval` if set
+struct Entry {
+    due: Instant,
+    interval: Option<Duration>,
+    /// Weak, so pending wakeups don't keep a dropped stream's [`Notify`] around
+    slot: Weak<Slot>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.due == other.due
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.due.cmp(&other.due)
+    }
+}
+
+/// One thread serving every ticker and deadline, soonest first
+#[derive(Default)]
+struct Timer {
+    entries: Mutex<BinaryHeap<Reverse<Entry>>>,
+    changed: Condvar,
+}
+
+/// The shared [`Timer`], started on first use
+fn timer() -> &'static Timer {
+    static TIMER: OnceLock<Timer> = OnceLock::new();
+
+    TIMER.get_or_init(|| {
+        std::thread::Builder::new()
+            .name(String::from("media-session-timer"))
+            .spawn(|| timer().run())
+            .expect("failed to spawn the timer thread");
+        Timer::default()
+    })
+}
+
+impl Timer {
+    fn schedule(&self, notify: &Notify, due: Instant, interval: Option<Duration>) {
+        self.entries.lock().unwrap().push(Reverse(Entry {
+            due,
+            interval,
+            slot: Arc::downgrade(&notify.waker),
+        }));
+        self.changed.notify_one();
+    }
+
+    fn run(&self) {
+        let mut entries = self.entries.lock().unwrap();
+
+        loop {
+            let Some(Reverse(next)) = entries.peek() else {
+                entries = self.changed.wait(entries).unwrap();
+                continue;
+            };
+
+            let now = Instant::now();
+            if next.due > now {
+                let timeout = next.due - now;
+                entries = self.changed.wait_timeout(entries, timeout).unwrap().0;
+                continue;
+            }
+
+            let Reverse(mut entry) = entries.pop().unwrap();
+            let Some(slot) = entry.slot.upgrade() else {
+                continue;
+            };
+
+            // Wakers may run executor code, which must not wait on this lock
+            drop(entries);
+            wake(&slot);
+            entries = self.entries.lock().unwrap();
+
+            if let Some(interval) = entry.interval {
+                // From now rather than `due`: a late tick doesn't cause a burst
+                entry.due = now + interval;
+                entries.push(Reverse(entry));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        task::Wake,
+    };
+
+    use super::*;
+
+    struct Count(AtomicUsize);
+
+    impl Wake for Count {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn counting(notify: &Notify) -> Arc<Count> {
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        notify.register(&Waker::from(Arc::clone(&count)));
+        count
+    }
+
+    #[test]
+    fn test_wake_after() {
+        let notify = Notify::default();
+        let count = counting(&notify);
+        notify.wake_after(Duration::from_millis(10));
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(count.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_pending_wakeup_does_not_hold_notify() {
+        let notify = Notify::default();
+        notify.wake_after(Duration::from_secs(3600));
+
+        let slot = Arc::downgrade(&notify.waker);
+        drop(notify);
+        assert!(slot.upgrade().is_none());
+    }
+}
@@ -0,0 +1,28 @@
+/// Platform family a backend talks to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    Windows,
+    /// Linux and other desktop Unixes with DBus/MPRIS
+    Linux,
+    MacOs,
+    /// No media session API (stub backend)
+    Unsupported,
+}
+
+impl Platform {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Windows => "windows",
+            Self::Linux => "linux",
+            Self::MacOs => "macos",
+            Self::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,18 @@
+/// Which controls the current session accepts.
+///
+/// Everything is `false` when there is no active session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[allow(clippy::struct_excessive_bools, reason = "plain set of flags")]
+pub struct PlaybackCapabilities {
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_stop: bool,
+    pub can_next: bool,
+    pub can_prev: bool,
+    pub can_seek: bool,
+    pub can_shuffle: bool,
+    pub can_repeat: bool,
+    pub can_set_rate: bool,
+    pub can_set_volume: bool,
+}
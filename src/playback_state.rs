@@ -1,12 +1,23 @@
 use crate::error::Error;
 use std::str::FromStr;
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum PlaybackState {
     #[default]
     Stopped,
     Paused,
     Playing,
+    /// Switching tracks; the timeline is not reliable until the next update
+    Changing,
+    /// Loading media, e.g. buffering before playback starts (Windows only)
+    Opening,
+    /// The player closed its media (Windows only)
+    Closed,
 }
 
 impl PlaybackState {
@@ -14,12 +25,20 @@ impl PlaybackState {
         Self::from_str(&s)
     }
 
+    /// Playing or about to (switching tracks, loading): the player holds the focus
+    pub(crate) fn is_active(self) -> bool {
+        matches!(self, Self::Playing | Self::Changing | Self::Opening)
+    }
+
     #[must_use]
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Stopped => "stopped",
             Self::Paused => "paused",
             Self::Playing => "playing",
+            Self::Changing => "changing",
+            Self::Opening => "opening",
+            Self::Closed => "closed",
         }
     }
 }
@@ -38,6 +57,9 @@ impl FromStr for PlaybackState {
             "stopped" => Ok(Self::Stopped),
             "paused" => Ok(Self::Paused),
             "playing" => Ok(Self::Playing),
+            "changing" => Ok(Self::Changing),
+            "opening" => Ok(Self::Opening),
+            "closed" => Ok(Self::Closed),
             "" => Err(Error::new("cannot parse playback state from empty string")),
             _ => Err(Error::new("cannot parse playback state")),
         }
@@ -0,0 +1,128 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    media_info::extrapolate_position,
+    notify::{Notify, MIN_TICK},
+    utils::micros_since_epoch,
+    MediaInfo, PlaybackState, PositionInfo,
+};
+
+/// What the position is extrapolated from, as of the last `update`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PositionAnchor {
+    state: PlaybackState,
+    duration: i64,
+    pos_info: PositionInfo,
+}
+
+impl PositionAnchor {
+    /// Anchored at `info.position`, which was extrapolated just now
+    pub fn new(info: &MediaInfo) -> Self {
+        Self {
+            state: info.state,
+            duration: info.duration,
+            pos_info: PositionInfo {
+                playback_rate: info.playback_rate,
+                pos_last_update: micros_since_epoch(),
+                pos_raw: info.position,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn position(&self, max_extrapolation: i64) -> i64 {
+        let pos_info = PositionInfo {
+            max_extrapolation,
+            ..self.pos_info.clone()
+        };
+
+        extrapolate_position(self.state, self.duration, &pos_info)
+    }
+}
+
+/// The extrapolated position at a fixed cadence, see [`MediaSession::position_stream`]
+///
+/// [`MediaSession::position_stream`]: crate::MediaSession::position_stream
+pub struct PositionStream {
+    anchor: Arc<Mutex<PositionAnchor>>,
+    ticker: Notify,
+    interval: Duration,
+    due: Instant,
+    max_extrapolation: i64,
+}
+
+impl PositionStream {
+    pub(crate) fn new(
+        anchor: Arc<Mutex<PositionAnchor>>,
+        interval: Duration,
+        max_extrapolation: i64,
+    ) -> Self {
+        let interval = interval.max(MIN_TICK);
+
+        Self {
+            anchor,
+            ticker: Notify::ticker(interval),
+            interval,
+            due: Instant::now(),
+            max_extrapolation,
+        }
+    }
+}
+
+impl Stream for PositionStream {
+    type Item = i64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let this = &mut *self;
+
+        // The session is gone, nothing will move anymore
+        if Arc::strong_count(&this.anchor) == 1 {
+            return Poll::Ready(None);
+        }
+
+        this.ticker.register(cx.waker());
+
+        let now = Instant::now();
+        if now < this.due {
+            return Poll::Pending;
+        }
+        this.due = now + this.interval;
+
+        Poll::Ready(Some(
+            this.anchor.lock().unwrap().position(this.max_extrapolation),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_extrapolates() {
+        let info = MediaInfo {
+            state: PlaybackState::Playing,
+            duration: 200_000_000,
+            position: 10_000_000,
+            ..Default::default()
+        };
+        let mut anchor = PositionAnchor::new(&info);
+        anchor.pos_info.pos_last_update -= 5_000_000;
+
+        assert!((15_000_000..16_000_000).contains(&anchor.position(10_000_000)));
+
+        // A report older than the cap
+        anchor.pos_info.pos_last_update -= 200_000_000;
+        assert_eq!(anchor.position(5_000_000), 10_000_000);
+
+        anchor.state = PlaybackState::Paused;
+        assert_eq!(anchor.position(60_000_000), 10_000_000);
+    }
+}
@@ -0,0 +1,10 @@
+use crate::MediaInfo;
+
+/// One of the players found by [`MediaSession::list_sessions`](crate::MediaSession::list_sessions)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SessionHandle {
+    /// Same as `info.source_app_id`
+    pub source_app_id: String,
+    pub info: MediaInfo,
+}
@@ -0,0 +1,111 @@
+use crate::{utils::micros_since_epoch, MediaInfo};
+
+/// A track seen by the session, from [`MediaSession::recent_tracks`](crate::MediaSession::recent_tracks)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TrackChange {
+    /// The track as first seen, without cover data (`cover_raw` and `cover_b64` empty)
+    pub info: MediaInfo,
+    /// UNIX microseconds
+    pub started_at: i64,
+    /// UNIX microseconds; `None` while it is still the current track.
+    ///
+    /// Compare `ended_at - started_at` with `info.duration` to tell a finished track
+    /// from a skipped one.
+    pub ended_at: Option<i64>,
+}
+
+/// The last `capacity` tracks, oldest first
+#[derive(Default)]
+pub(crate) struct TrackHistory {
+    capacity: usize,
+    entries: Vec<TrackChange>,
+}
+
+impl TrackHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn entries(&self) -> &[TrackChange] {
+        &self.entries
+    }
+
+    /// Note the track in `info`, closing the previous one if it changed
+    pub fn record(&mut self, info: &MediaInfo) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let current = self
+            .entries
+            .last_mut()
+            .filter(|last| last.ended_at.is_none());
+        if current
+            .as_ref()
+            .is_some_and(|current| current.info.is_same_track(info))
+        {
+            return;
+        }
+
+        let now = micros_since_epoch();
+        if let Some(current) = current {
+            current.ended_at = Some(now);
+        }
+
+        // Nothing loaded
+        if info.track_id.is_empty() {
+            return;
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.remove(0);
+        }
+
+        self.entries.push(TrackChange {
+            info: MediaInfo {
+                cover_raw: Vec::new(),
+                cover_b64: String::new(),
+                ..info.clone()
+            },
+            started_at: now,
+            ended_at: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str) -> MediaInfo {
+        MediaInfo {
+            track_id: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_capped_and_closed() {
+        let mut history = TrackHistory::new(2);
+
+        for id in ["a", "a", "b", "c"] {
+            history.record(&track(id));
+        }
+
+        let ids: Vec<&str> = history
+            .entries()
+            .iter()
+            .map(|t| t.info.track_id.as_str())
+            .collect();
+        assert_eq!(ids, ["b", "c"]);
+        assert!(history.entries()[0].ended_at.is_some());
+        assert!(history.entries()[1].ended_at.is_none());
+
+        history.record(&MediaInfo::default());
+        assert!(history.entries()[1].ended_at.is_some());
+    }
+}
@@ -1,3 +1,8 @@
+/// Playback controls, implemented by [`MediaSession`](crate::MediaSession) on every
+/// platform with the same blocking signatures.
+///
+/// Without an active player the controls do nothing and return `Ok(())`; backends
+/// without control support return an error.
 pub trait MediaSessionControls {
     fn toggle_pause(&self) -> crate::Result<()>;
     fn pause(&self) -> crate::Result<()>;
@@ -5,4 +10,43 @@ pub trait MediaSessionControls {
     fn stop(&self) -> crate::Result<()>;
     fn next(&self) -> crate::Result<()>;
     fn prev(&self) -> crate::Result<()>;
+    /// Seek to `position_micros` from the start of the track
+    fn set_position(&self, position_micros: i64) -> crate::Result<()>;
+    /// Seek `offset_micros` forward (or back, if negative) from the current position,
+    /// staying within the track
+    fn seek_by(&self, offset_micros: i64) -> crate::Result<()>;
+    /// Set the player volume, `0.0..=1.0`
+    fn set_volume(&self, level: f64) -> crate::Result<()>;
+    fn set_shuffle(&self, on: bool) -> crate::Result<()>;
+    fn set_repeat(&self, mode: crate::RepeatMode) -> crate::Result<()>;
+    /// Set the playback speed (`1.0` is normal), clamped to the player's range where
+    /// it advertises one
+    fn set_rate(&self, rate: f64) -> crate::Result<()>;
+    /// The controls the current player accepts, empty without one
+    fn supported_controls(&self) -> crate::ControlFlags;
+}
+
+/// Managing the player application itself rather than its playback
+///
+/// Without an active player these do nothing and return `Ok(())`; backends that
+/// can't reach the application return an [`ErrorKind::Unsupported`] error.
+///
+/// [`ErrorKind::Unsupported`]: crate::ErrorKind::Unsupported
+pub trait MediaSessionApp {
+    /// Bring the player's window to the front, e.g. when a now-playing widget is
+    /// clicked
+    fn raise(&self) -> crate::Result<()>;
+    /// Ask the player to exit
+    fn quit(&self) -> crate::Result<()>;
+    /// Whether [`raise`](Self::raise) would do anything, `false` without a player
+    fn can_raise(&self) -> bool;
+    /// Whether [`quit`](Self::quit) would do anything, `false` without a player
+    fn can_quit(&self) -> bool;
+}
+
+/// Identifies the implementation behind a session, for diagnostics
+pub trait MediaSessionBackend {
+    /// Human-readable name, e.g. `"WinRT (GSMTC)"`
+    fn backend_name(&self) -> &'static str;
+    fn platform(&self) -> crate::Platform;
 }
@@ -9,9 +9,39 @@ pub fn micros_since_epoch() -> i64 {
         .as_micros() as i64
 }
 
+/// `duration` in microseconds, saturating
+pub fn duration_micros(duration: time::Duration) -> i64 {
+    i64::try_from(duration.as_micros()).unwrap_or(i64::MAX)
+}
+
 /// Convert Windows NT time to UNIX time
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "stub")))]
 pub fn nt_to_unix(time: i64) -> i64 {
     const NT_UNIX_MICROSEC_DIFF: i64 = 11_644_473_600_000_000;
     time - NT_UNIX_MICROSEC_DIFF
 }
+
+pub const UNKNOWN_MIME: &str = "application/octet-stream";
+
+/// MIME type of an image, guessed from its magic bytes
+#[allow(dead_code, reason = "unused by backends without cover art")]
+pub fn image_mime(bytes: &[u8]) -> &'static str {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xFF, 0xD8, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'B', b'M', ..] => "image/bmp",
+        _ => UNKNOWN_MIME,
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across runs and platforms
+#[allow(dead_code, reason = "unused by backends without cover art")]
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}